@@ -0,0 +1,775 @@
+//! Check that the rust types of the calldata encode like the types of a cairo 1 contract ABI, so
+//! an upgrade of the contract that changes a struct is caught by a unit test instead of failing
+//! calls.
+//!
+//! ```ignore
+//! let abi = Abi::from_file("target/dev/zklink_Zklink.contract_class.json")?;
+//! let mismatches = abi.check_inputs::<(Vec<CommitBlockInfo>,)>("commitBlocks")?;
+//! assert!(mismatches.is_empty(), "{mismatches:?}");
+//! ```
+
+use crate::encoder::strip_module_paths;
+use crate::error::AbiError;
+use crate::layout::{FeltLayout, Layout};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// The types which are a single element, by their name without module
+const FELT_TYPES: [&str; 10] = [
+    "felt252",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u128",
+    "ContractAddress",
+    "ClassHash",
+    "EthAddress",
+    "bytes31",
+];
+
+/// Deeper types are assumed to be recursive
+const MAX_DEPTH: usize = 64;
+
+/// A type of the ABI with the struct and enum names resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    /// A single element, e.g. `felt252`, `u64` or `ContractAddress`
+    Felt(String),
+    Struct {
+        name: String,
+        members: Vec<(String, AbiType)>,
+    },
+    Tuple(Vec<AbiType>),
+    Array(Box<AbiType>),
+    Span(Box<AbiType>),
+    Enum {
+        name: String,
+        variants: Vec<(String, AbiType)>,
+    },
+}
+
+/// Displayed with the short name of the type, e.g. `Array<u256>`.
+impl Display for AbiType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Felt(name) | Self::Struct { name, .. } | Self::Enum { name, .. } => {
+                write!(f, "{name}")
+            }
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{sep}{item}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Array(element) => write!(f, "Array<{element}>"),
+            Self::Span(element) => write!(f, "Span<{element}>"),
+        }
+    }
+}
+
+/// How a rust type differs from the ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The types are encoded differently, e.g. `u256` and `felt252`, or `Array` and `Span`
+    Type {
+        expected: String,
+        found: String,
+    },
+    FieldCount {
+        expected: usize,
+        found: usize,
+    },
+    FieldName {
+        expected: String,
+        found: &'static str,
+    },
+    VariantCount {
+        expected: usize,
+        found: usize,
+    },
+    VariantName {
+        expected: String,
+        found: &'static str,
+    },
+}
+
+/// A difference between the inputs or outputs of a function and a rust type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub function: String,
+    /// The path of the parameter in the ABI, e.g. `info.onchain_operations[].eth_witness`
+    pub path: String,
+    pub kind: MismatchKind,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} `{}`: ", self.function, self.path)?;
+        match &self.kind {
+            MismatchKind::Type { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            MismatchKind::FieldCount { expected, found } => {
+                write!(f, "expected {expected} fields, found {found}")
+            }
+            MismatchKind::FieldName { expected, found } => {
+                write!(f, "expected field {expected}, found {found}")
+            }
+            MismatchKind::VariantCount { expected, found } => {
+                write!(f, "expected {expected} variants, found {found}")
+            }
+            MismatchKind::VariantName { expected, found } => {
+                write!(f, "expected variant {expected}, found {found}")
+            }
+        }
+    }
+}
+
+/// An entry of the ABI json, the entries which don't declare types or functions are ignored
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Function(Function),
+    L1Handler(Function),
+    Constructor(Function),
+    Interface {
+        items: Vec<Entry>,
+    },
+    Struct {
+        name: String,
+        members: Vec<Member>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Member>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Function {
+    name: String,
+    #[serde(default)]
+    inputs: Vec<Member>,
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+#[derive(Deserialize)]
+struct Member {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+/// The ABI of a cairo 1 contract
+pub struct Abi {
+    functions: HashMap<String, Function>,
+    structs: HashMap<String, Vec<Member>>,
+    enums: HashMap<String, Vec<Member>>,
+}
+
+impl Abi {
+    /// Parse the ABI, or a contract class whose `abi` field is the ABI or its json string.
+    pub fn from_json(json: &str) -> Result<Self, AbiError> {
+        let invalid = |e: serde_json::Error| AbiError::InvalidJson(e.to_string());
+        let value: serde_json::Value = serde_json::from_str(json).map_err(invalid)?;
+        let entries = match value {
+            serde_json::Value::Object(mut class) => match class.remove("abi") {
+                Some(serde_json::Value::String(abi)) => serde_json::from_str(&abi),
+                Some(abi) => serde_json::from_value(abi),
+                None => return Err(AbiError::InvalidJson("missing abi".to_string())),
+            },
+            abi => serde_json::from_value(abi),
+        }
+        .map_err(invalid)?;
+
+        let mut abi = Self {
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+        };
+        abi.add_entries(entries);
+        Ok(abi)
+    }
+
+    /// Read the ABI or contract class json at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AbiError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    fn add_entries(&mut self, entries: Vec<Entry>) {
+        for entry in entries {
+            match entry {
+                Entry::Function(f) | Entry::L1Handler(f) | Entry::Constructor(f) => {
+                    self.functions.entry(f.name.clone()).or_insert(f);
+                }
+                Entry::Interface { items } => self.add_entries(items),
+                Entry::Struct { name, members } => {
+                    self.structs.insert(name, members);
+                }
+                Entry::Enum { name, variants } => {
+                    self.enums.insert(name, variants);
+                }
+                Entry::Other => {}
+            }
+        }
+    }
+
+    /// The names of the functions, including those of the interfaces
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    /// The parameters of `function` with their resolved types.
+    pub fn inputs(&self, function: &str) -> Result<Vec<(String, AbiType)>, AbiError> {
+        self.function(function)?
+            .inputs
+            .iter()
+            .map(|input| Ok((input.name.clone(), self.resolve(&input.ty, 0)?)))
+            .collect()
+    }
+
+    /// The types returned by `function`.
+    pub fn outputs(&self, function: &str) -> Result<Vec<AbiType>, AbiError> {
+        self.function(function)?
+            .outputs
+            .iter()
+            .map(|output| self.resolve(&output.ty, 0))
+            .collect()
+    }
+
+    /// Compare the calldata of `function` with `T`, the tuple of its parameters, e.g.
+    /// `(StoredBlockInfo,)` for a single parameter. Any other type is compared as the only
+    /// parameter, so a parameter which is a tuple is `((u8, u64),)`, see [Abi::check_input].
+    pub fn check_inputs<T: FeltLayout>(&self, function: &str) -> Result<Vec<Mismatch>, AbiError> {
+        let inputs = self.inputs(function)?;
+        let layout = T::felt_layout()?;
+        let found = match &layout {
+            Layout::Tuple(items) => items.iter().map(|l| (None, l)).collect(),
+            layout => vec![(None, layout)],
+        };
+        let mut check = Check::new(function);
+        let expected = inputs.iter().map(|(n, t)| (Some(n.as_str()), t)).collect();
+        check.compare_fields(expected, found, "", false);
+        Ok(check.mismatches)
+    }
+
+    /// Compare the parameter `param` of `function` with `T`.
+    pub fn check_input<T: FeltLayout>(
+        &self,
+        function: &str,
+        param: &str,
+    ) -> Result<Vec<Mismatch>, AbiError> {
+        let inputs = self.inputs(function)?;
+        let (name, input) = inputs
+            .iter()
+            .find(|(name, _)| name == param)
+            .ok_or_else(|| AbiError::UnknownParameter {
+                function: function.to_string(),
+                param: param.to_string(),
+            })?;
+        let mut check = Check::new(function);
+        check.compare(input, &T::felt_layout()?, name.clone());
+        Ok(check.mismatches)
+    }
+
+    /// Compare the return value of `function` with `T`, which is `()` if nothing is returned.
+    pub fn check_outputs<T: FeltLayout>(&self, function: &str) -> Result<Vec<Mismatch>, AbiError> {
+        let outputs = self.outputs(function)?;
+        let layout = T::felt_layout()?;
+        let mut check = Check::new(function);
+        match outputs.as_slice() {
+            [output] => check.compare(output, &layout, "output".to_string()),
+            _ => check.compare(&AbiType::Tuple(outputs), &layout, "output".to_string()),
+        }
+        Ok(check.mismatches)
+    }
+
+    fn function(&self, name: &str) -> Result<&Function, AbiError> {
+        self.functions
+            .get(name)
+            .ok_or_else(|| AbiError::UnknownFunction(name.to_string()))
+    }
+
+    /// Resolve the type named `ty` in the ABI, e.g. `core::array::Span::<core::felt252>`.
+    fn resolve(&self, ty: &str, depth: usize) -> Result<AbiType, AbiError> {
+        if depth > MAX_DEPTH {
+            return Err(AbiError::UnknownType(format!("recursive type {ty}")));
+        }
+        let ty = ty.trim();
+        let resolve_all = |types: &mut dyn Iterator<Item = &str>| {
+            types
+                .map(|ty| self.resolve(ty, depth + 1))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let resolve_members = |members: &[Member]| {
+            members
+                .iter()
+                .map(|m| Ok((m.name.clone(), self.resolve(&m.ty, depth + 1)?)))
+                .collect::<Result<Vec<_>, AbiError>>()
+        };
+        // `core::bool` is declared as an enum, which is encoded like the rust `bool`
+        if ty == "core::bool" {
+            return Ok(AbiType::Felt("bool".to_string()));
+        }
+        if let Some(items) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            return Ok(AbiType::Tuple(resolve_all(&mut split_types(items))?));
+        }
+        if let Some(members) = self.structs.get(ty) {
+            return Ok(AbiType::Struct {
+                name: short_name(ty),
+                members: resolve_members(members)?,
+            });
+        }
+        if let Some(variants) = self.enums.get(ty) {
+            return Ok(AbiType::Enum {
+                name: short_name(ty),
+                variants: resolve_members(variants)?,
+            });
+        }
+        if let Some(element) = generic_argument(ty, "core::array::Array") {
+            return Ok(AbiType::Array(Box::new(self.resolve(element, depth + 1)?)));
+        }
+        if let Some(element) = generic_argument(ty, "core::array::Span") {
+            return Ok(AbiType::Span(Box::new(self.resolve(element, depth + 1)?)));
+        }
+        let name = short_name(ty);
+        if FELT_TYPES.contains(&name.as_str()) {
+            return Ok(AbiType::Felt(name));
+        }
+        if name == "u256" {
+            // the ABI usually declares it, with the layout of `crate::U256`
+            let low = ("low".to_string(), AbiType::Felt("u128".to_string()));
+            let high = ("high".to_string(), AbiType::Felt("u128".to_string()));
+            return Ok(AbiType::Struct {
+                name,
+                members: vec![low, high],
+            });
+        }
+        Err(AbiError::UnknownType(ty.to_string()))
+    }
+}
+
+/// The name without modules, e.g. `Option<u32>` for `core::option::Option::<core::integer::u32>`.
+fn short_name(ty: &str) -> String {
+    strip_module_paths(&ty.replace("::<", "<"))
+}
+
+/// The argument of the generic type `base`, e.g. `T` of `core::array::Array::<T>`
+fn generic_argument<'a>(ty: &'a str, base: &str) -> Option<&'a str> {
+    ty.strip_prefix(base)?
+        .strip_prefix("::<")?
+        .strip_suffix('>')
+}
+
+/// Split the types of a tuple at the commas which aren't nested in another type.
+fn split_types(types: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut items = vec![];
+    for (i, c) in types.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&types[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&types[start..]);
+    items.into_iter().filter(|item| !item.trim().is_empty())
+}
+
+/// The named or positional elements of a rust struct or tuple.
+fn found_elements(layout: &Layout) -> Vec<(Option<&'static str>, &Layout)> {
+    match layout {
+        Layout::Struct { fields, .. } => fields.iter().map(|(n, l)| (Some(*n), l)).collect(),
+        Layout::Tuple(items) => items.iter().map(|l| (None, l)).collect(),
+        layout => vec![(None, layout)],
+    }
+}
+
+/// Whether a rust element of type `found` can be encoded as the cairo type `expected`
+fn felt_compatible(expected: &str, found: &str) -> bool {
+    // the rust strings are any element
+    expected == found || (found == "felt252" && !expected.starts_with('u') && expected != "bool")
+}
+
+/// The mismatches found so far
+struct Check<'a> {
+    function: &'a str,
+    mismatches: Vec<Mismatch>,
+}
+
+impl<'a> Check<'a> {
+    fn new(function: &'a str) -> Self {
+        Self {
+            function,
+            mismatches: vec![],
+        }
+    }
+
+    fn report(&mut self, path: String, kind: MismatchKind) {
+        self.mismatches.push(Mismatch {
+            function: self.function.to_string(),
+            path,
+            kind,
+        });
+    }
+
+    fn compare(&mut self, expected: &AbiType, found: &Layout, path: String) {
+        match (expected, found) {
+            (AbiType::Felt(e), Layout::Felt(f)) if felt_compatible(e, f) => {}
+            (AbiType::Struct { members, .. }, Layout::Struct { .. } | Layout::Tuple(_)) => {
+                let expected = members.iter().map(|(n, t)| (Some(n.as_str()), t)).collect();
+                self.compare_fields(expected, found_elements(found), &path, false);
+            }
+            (AbiType::Tuple(items), Layout::Struct { .. } | Layout::Tuple(_)) => {
+                let expected = items.iter().map(|t| (None, t)).collect();
+                self.compare_fields(expected, found_elements(found), &path, false);
+            }
+            (AbiType::Array(e), Layout::Seq(f)) | (AbiType::Span(e), Layout::Span(f)) => {
+                self.compare(e, f, format!("{path}[]"))
+            }
+            (
+                AbiType::Enum { variants, .. },
+                Layout::Enum {
+                    variants: found, ..
+                },
+            ) => {
+                let expected = variants
+                    .iter()
+                    .map(|(n, t)| (Some(n.as_str()), t))
+                    .collect();
+                let found = found.iter().map(|(n, l)| (Some(*n), l)).collect();
+                self.compare_fields(expected, found, &path, true);
+            }
+            _ => self.report(
+                path,
+                MismatchKind::Type {
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                },
+            ),
+        }
+    }
+
+    /// Compare the members of a struct or tuple, or the variants of an enum.
+    fn compare_fields(
+        &mut self,
+        expected: Vec<(Option<&str>, &AbiType)>,
+        found: Vec<(Option<&'static str>, &Layout)>,
+        path: &str,
+        variants: bool,
+    ) {
+        if expected.len() != found.len() {
+            let (expected, found) = (expected.len(), found.len());
+            let kind = if variants {
+                MismatchKind::VariantCount { expected, found }
+            } else {
+                MismatchKind::FieldCount { expected, found }
+            };
+            self.report(path.to_string(), kind);
+        }
+        for (i, ((name, expected), (found_name, found))) in
+            expected.into_iter().zip(found).enumerate()
+        {
+            let segment = name.map_or_else(|| i.to_string(), str::to_string);
+            let path = match (path, variants) {
+                ("", _) => segment,
+                (path, true) => format!("{path}::{segment}"),
+                (path, false) => format!("{path}.{segment}"),
+            };
+            if let (Some(name), Some(found_name)) = (name, found_name) {
+                if name != found_name {
+                    let (expected, found) = (name.to_string(), found_name);
+                    let kind = if variants {
+                        MismatchKind::VariantName { expected, found }
+                    } else {
+                        MismatchKind::FieldName { expected, found }
+                    };
+                    self.report(path.clone(), kind);
+                }
+            }
+            self.compare(expected, found, path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::array::Span;
+    use crate::proto::StoredBlockInfo;
+    use crate::U256;
+    use serde::Deserialize;
+
+    const ABI: &str = r#"[
+        {
+            "type": "impl",
+            "name": "ZklinkImpl",
+            "interface_name": "zklink::IZklink"
+        },
+        {
+            "type": "struct",
+            "name": "core::integer::u256",
+            "members": [
+                {"name": "low", "type": "core::integer::u128"},
+                {"name": "high", "type": "core::integer::u128"}
+            ]
+        },
+        {
+            "type": "struct",
+            "name": "zklink::StoredBlockInfo",
+            "members": [
+                {"name": "block_number", "type": "core::integer::u64"},
+                {"name": "priority_operations", "type": "core::integer::u64"},
+                {"name": "pending_onchain_operations_hash", "type": "core::integer::u256"},
+                {"name": "timestamp", "type": "core::integer::u64"},
+                {"name": "state_hash", "type": "core::integer::u256"},
+                {"name": "commitment", "type": "core::integer::u256"},
+                {"name": "sync_hash", "type": "core::integer::u256"}
+            ]
+        },
+        {
+            "type": "struct",
+            "name": "zklink::StoredBlockInfoV2",
+            "members": [
+                {"name": "block_number", "type": "core::integer::u64"},
+                {"name": "priority_operations", "type": "core::integer::u64"},
+                {"name": "pending_onchain_operations_hash", "type": "core::integer::u256"},
+                {"name": "timestamp", "type": "core::integer::u64"},
+                {"name": "state_hash", "type": "core::felt252"},
+                {"name": "commitment", "type": "core::integer::u256"},
+                {"name": "sync_hash", "type": "core::integer::u256"},
+                {"name": "chain_id", "type": "core::integer::u8"}
+            ]
+        },
+        {
+            "type": "enum",
+            "name": "core::option::Option::<core::integer::u32>",
+            "variants": [
+                {"name": "Some", "type": "core::integer::u32"},
+                {"name": "None", "type": "()"}
+            ]
+        },
+        {
+            "type": "enum",
+            "name": "zklink::Status",
+            "variants": [
+                {"name": "Pending", "type": "()"},
+                {"name": "Executed", "type": "(core::integer::u64, core::bool)"}
+            ]
+        },
+        {
+            "type": "interface",
+            "name": "zklink::IZklink",
+            "items": [
+                {
+                    "type": "function",
+                    "name": "test_stored_block_info",
+                    "inputs": [{"name": "info", "type": "zklink::StoredBlockInfo"}],
+                    "outputs": [],
+                    "state_mutability": "external"
+                },
+                {
+                    "type": "function",
+                    "name": "test_stored_block_info_v2",
+                    "inputs": [{"name": "info", "type": "zklink::StoredBlockInfoV2"}],
+                    "outputs": [],
+                    "state_mutability": "external"
+                },
+                {
+                    "type": "function",
+                    "name": "test_u128_span",
+                    "inputs": [
+                        {"name": "data", "type": "core::array::Span::<core::integer::u128>"},
+                        {"name": "fee", "type": "core::option::Option::<core::integer::u32>"}
+                    ],
+                    "outputs": [{"type": "core::integer::u256"}],
+                    "state_mutability": "view"
+                },
+                {
+                    "type": "function",
+                    "name": "test_pair",
+                    "inputs": [{"name": "pair", "type": "(core::integer::u8, core::integer::u64)"}],
+                    "outputs": [],
+                    "state_mutability": "external"
+                },
+                {
+                    "type": "function",
+                    "name": "status",
+                    "inputs": [{"name": "owner", "type": "core::starknet::contract_address::ContractAddress"}],
+                    "outputs": [{"type": "zklink::Status"}],
+                    "state_mutability": "view"
+                }
+            ]
+        },
+        {
+            "type": "event",
+            "name": "zklink::Event",
+            "kind": "enum",
+            "variants": []
+        }
+    ]"#;
+
+    fn abi() -> Abi {
+        Abi::from_json(ABI).unwrap()
+    }
+
+    #[test]
+    fn test_parse_abi() {
+        let abi = abi();
+        let mut names: Vec<_> = abi.function_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "status",
+                "test_pair",
+                "test_stored_block_info",
+                "test_stored_block_info_v2",
+                "test_u128_span"
+            ]
+        );
+        let inputs = abi.inputs("test_u128_span").unwrap();
+        assert_eq!(inputs[0].1.to_string(), "Span<u128>");
+        assert_eq!(inputs[1].1.to_string(), "Option<u32>");
+        assert_eq!(abi.outputs("status").unwrap()[0].to_string(), "Status");
+        assert!(matches!(
+            abi.inputs("missing"),
+            Err(AbiError::UnknownFunction(_))
+        ));
+
+        // a fetched class has the abi as a json string
+        let class = serde_json::json!({ "sierra_program": [], "abi": ABI }).to_string();
+        let class = Abi::from_json(&class).unwrap();
+        assert_eq!(class.function_names().count(), 5);
+
+        assert!(matches!(
+            Abi::from_file("missing.contract_class.json"),
+            Err(AbiError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_struct() {
+        let abi = abi();
+        let mismatches = abi
+            .check_inputs::<StoredBlockInfo>("test_stored_block_info")
+            .unwrap();
+        assert_eq!(mismatches, []);
+        assert_eq!(
+            abi.check_input::<StoredBlockInfo>("test_stored_block_info", "info")
+                .unwrap(),
+            []
+        );
+        assert_eq!(
+            abi.check_inputs::<(StoredBlockInfo,)>("test_stored_block_info")
+                .unwrap(),
+            []
+        );
+
+        let mismatches = abi
+            .check_inputs::<StoredBlockInfo>("test_stored_block_info_v2")
+            .unwrap();
+        let mismatches: Vec<_> = mismatches.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            mismatches,
+            [
+                "test_stored_block_info_v2 `info`: expected 8 fields, found 7",
+                "test_stored_block_info_v2 `info.state_hash`: expected felt252, found \
+                 U256 { low: u128, high: u128 }",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_tuple_param() {
+        let abi = abi();
+        assert_eq!(abi.check_inputs::<((u8, u64),)>("test_pair").unwrap(), []);
+        assert_eq!(
+            abi.check_input::<(u8, u64)>("test_pair", "pair").unwrap(),
+            []
+        );
+        // the tuple is the list of parameters
+        let mismatches = abi.check_inputs::<(u8, u64)>("test_pair").unwrap();
+        assert_eq!(
+            mismatches[0].kind,
+            MismatchKind::FieldCount {
+                expected: 1,
+                found: 2
+            }
+        );
+        assert!(matches!(
+            abi.check_input::<u8>("test_pair", "missing"),
+            Err(AbiError::UnknownParameter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_sequences_and_enums() {
+        let abi = abi();
+        assert_eq!(
+            abi.check_inputs::<(Span<u128>, Option<u32>)>("test_u128_span")
+                .unwrap(),
+            []
+        );
+        let mismatches = abi
+            .check_inputs::<(Vec<u128>, Option<u64>)>("test_u128_span")
+            .unwrap();
+        let paths: Vec<_> = mismatches.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, ["data", "fee::Some"]);
+        assert_eq!(abi.check_outputs::<U256>("test_u128_span").unwrap(), []);
+        assert_eq!(
+            abi.check_outputs::<u128>("test_u128_span").unwrap().len(),
+            1
+        );
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        enum Status {
+            Pending,
+            Executed(u64, bool),
+        }
+        assert_eq!(abi.check_outputs::<Status>("status").unwrap(), []);
+        assert_eq!(abi.check_inputs::<String>("status").unwrap(), []);
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        enum Renamed {
+            Waiting,
+            Executed(u64, bool),
+            Failed,
+        }
+        let mismatches = abi.check_outputs::<Renamed>("status").unwrap();
+        assert_eq!(
+            mismatches.iter().map(|m| &m.kind).collect::<Vec<_>>(),
+            [
+                &MismatchKind::VariantCount {
+                    expected: 2,
+                    found: 3
+                },
+                &MismatchKind::VariantName {
+                    expected: "Pending".to_string(),
+                    found: "Waiting"
+                },
+            ]
+        );
+    }
+}
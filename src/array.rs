@@ -0,0 +1,340 @@
+//! Wrappers that choose the cairo layout of sequences per field.
+//!
+//! | rust                 | cairo                     | layout                      |
+//! |----------------------|---------------------------|-----------------------------|
+//! | `Vec<T>`, [Span]     | `Array<T>`, `Span<T>`     | length, then the elements   |
+//! | [FixedArray], tuples | fixed size arrays, tuples | the elements                |
+//! | [Felts]              | the rest of the calldata  | the remaining elements      |
+//!
+//! A `felt252` is a [Felt], or a [FieldElement] with `#[serde(with = "stark_util::array::felt")]`.
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::types::FieldElement;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The newtype name of [Felts], the decoder reads the remaining elements when it's deserialized.
+pub(crate) const FELTS_NAME: &str = "$stark_util::Felts";
+
+/// A `felt252`, serialized as a `0x` prefixed hex string which is encoded as the element itself.
+///
+/// The serde impls of [FieldElement] use decimal strings, which can't be told apart from the hex
+/// of the other strings, so a [FieldElement] field is wrapped in a [Felt] or uses [felt].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Felt(pub FieldElement);
+
+impl From<FieldElement> for Felt {
+    fn from(value: FieldElement) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Felt> for FieldElement {
+    fn from(value: Felt) -> Self {
+        value.0
+    }
+}
+
+impl Serialize for Felt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Felt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        FieldElement::from_hex_be(&s)
+            .map(Felt)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Serde adapter of a [FieldElement], or a `Box`, `Rc` or `Arc` of it, serialized like a [Felt],
+/// for use with `#[serde(with = "stark_util::array::felt")]`.
+pub mod felt {
+    use super::Felt;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use starknet::core::types::FieldElement;
+    use std::borrow::Borrow;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Borrow<FieldElement>,
+        S: Serializer,
+    {
+        Felt(*value.borrow()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<FieldElement>,
+        D: Deserializer<'de>,
+    {
+        Felt::deserialize(deserializer).map(|felt| T::from(felt.0))
+    }
+}
+
+/// The newtype name of [Span], which tells it from `Vec<T>` in its [crate::layout::Layout].
+pub(crate) const SPAN_NAME: &str = "$stark_util::Span";
+
+/// The cairo `Span<T>`, length prefixed like `Vec<T>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename = "$stark_util::Span")]
+pub struct Span<T>(pub Vec<T>);
+
+impl<T> From<Vec<T>> for Span<T> {
+    fn from(value: Vec<T>) -> Self {
+        Self(value)
+    }
+}
+
+/// An array of `N` elements without length prefix, for any `N`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FixedArray<T, const N: usize>(pub [T; N]);
+
+impl<T, const N: usize> From<[T; N]> for FixedArray<T, N> {
+    fn from(value: [T; N]) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for FixedArray<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for item in &self.0 {
+            tuple.serialize_element(item)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for FixedArray<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+            type Value = FixedArray<T, N>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an array of {N} elements")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut items = Vec::with_capacity(N);
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                let len = items.len();
+                let array = items
+                    .try_into()
+                    .map_err(|_| de::Error::invalid_length(len, &self))?;
+                Ok(FixedArray(array))
+            }
+        }
+
+        deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+    }
+}
+
+/// The remaining elements without length prefix, e.g. the calldata forwarded by a contract. It
+/// must be the last field of the decoded value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Felts(pub Vec<FieldElement>);
+
+impl From<Vec<FieldElement>> for Felts {
+    fn from(value: Vec<FieldElement>) -> Self {
+        Self(value)
+    }
+}
+
+impl Serialize for Felts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Elements<'a>(&'a [FieldElement]);
+
+        impl Serialize for Elements<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut tuple = serializer.serialize_tuple(self.0.len())?;
+                for element in self.0 {
+                    tuple.serialize_element(&Felt(*element))?;
+                }
+                tuple.end()
+            }
+        }
+
+        serializer.serialize_newtype_struct(FELTS_NAME, &Elements(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Felts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FeltsVisitor;
+
+        impl<'de> Visitor<'de> for FeltsVisitor {
+            type Value = Felts;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "field elements")
+            }
+
+            // self-describing formats deserialize the elements as a sequence
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                let elements = Vec::<Felt>::deserialize(deserializer)?;
+                Ok(Felts(elements.into_iter().map(|e| e.0).collect()))
+            }
+
+            // the decoder visits the remaining elements
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut elements = vec![];
+                while let Some(Felt(element)) = seq.next_element()? {
+                    elements.push(element);
+                }
+                Ok(Felts(elements))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(FELTS_NAME, FeltsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::EncodeError;
+    use crate::{from_slice, to_field_elements};
+
+    fn felts(v: &[u64]) -> Vec<FieldElement> {
+        v.iter().map(|x| FieldElement::from(*x)).collect()
+    }
+
+    #[test]
+    fn test_span_layout() {
+        let span = Span(vec![
+            Span(vec![Felt(FieldElement::from(10u8))]),
+            Span(vec![]),
+            Span(vec![Felt(FieldElement::ONE), Felt(FieldElement::MAX)]),
+        ]);
+        let encoded = to_field_elements(&span).unwrap();
+        let mut expected = felts(&[3, 1, 10, 0, 2, 1]);
+        expected.push(FieldElement::MAX);
+        assert_eq!(encoded, expected);
+        assert_eq!(from_slice::<Span<Span<Felt>>>(&encoded).unwrap(), span);
+
+        // self-describing formats see the `Vec`
+        let json = serde_json::to_string(&span).unwrap();
+        let vecs: Vec<_> = span.0.iter().map(|s| &s.0).collect();
+        assert_eq!(json, serde_json::to_string(&vecs).unwrap());
+        assert_eq!(
+            serde_json::from_str::<Span<Span<Felt>>>(&json).unwrap(),
+            span
+        );
+    }
+
+    #[test]
+    fn test_fixed_array() {
+        let array = FixedArray(std::array::from_fn::<u64, 40, _>(|i| i as u64));
+        let encoded = to_field_elements(&array).unwrap();
+        assert_eq!(encoded, felts(&array.0));
+        assert_eq!(from_slice::<FixedArray<u64, 40>>(&encoded).unwrap(), array);
+        assert!(from_slice::<FixedArray<u64, 40>>(&encoded[1..]).is_err());
+
+        let nested = (FixedArray([1u8, 2]), Span(vec![FixedArray([3u8, 4])]));
+        let encoded = to_field_elements(&nested).unwrap();
+        assert_eq!(encoded, felts(&[1, 2, 1, 3, 4]));
+        assert_eq!(
+            from_slice::<(FixedArray<u8, 2>, Span<FixedArray<u8, 2>>)>(&encoded).unwrap(),
+            nested
+        );
+    }
+
+    #[test]
+    fn test_felts() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Call {
+            selector: Felt,
+            calldata: Felts,
+        }
+        let call = Call {
+            selector: Felt(FieldElement::from(0xabcu32)),
+            calldata: Felts(felts(&[0, 12, 3])),
+        };
+        let encoded = to_field_elements(&call).unwrap();
+        assert_eq!(encoded, felts(&[0xabc, 0, 12, 3]));
+        assert_eq!(from_slice::<Call>(&encoded).unwrap(), call);
+        let empty: Call = from_slice(&encoded[..1]).unwrap();
+        assert!(empty.calldata.0.is_empty());
+
+        let json = serde_json::to_string(&call).unwrap();
+        assert_eq!(
+            json,
+            r#"{"selector":"0xabc","calldata":["0x0","0xc","0x3"]}"#
+        );
+        assert_eq!(serde_json::from_str::<Call>(&json).unwrap(), call);
+    }
+
+    #[test]
+    fn test_field_element_fields() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Raw {
+            #[serde(with = "felt")]
+            a: FieldElement,
+            #[serde(with = "felt")]
+            b: Box<FieldElement>,
+            #[serde(with = "felt")]
+            c: Rc<FieldElement>,
+            #[serde(with = "felt")]
+            d: Arc<FieldElement>,
+            e: Box<Felt>,
+        }
+        let raw = Raw {
+            a: FieldElement::from(10u8),
+            b: Box::new(FieldElement::from(10u8)),
+            c: Rc::new(FieldElement::MAX),
+            d: Arc::new(FieldElement::from(255u8)),
+            e: Box::new(Felt(FieldElement::from(16u8))),
+        };
+        let encoded = to_field_elements(&raw).unwrap();
+        let mut expected = felts(&[10, 10]);
+        expected.push(FieldElement::MAX);
+        expected.extend(felts(&[255, 16]));
+        assert_eq!(encoded, expected);
+        assert_eq!(from_slice::<Raw>(&encoded).unwrap(), raw);
+
+        let json = serde_json::to_value(&raw).unwrap();
+        assert_eq!(json["b"], "0xa");
+        assert_eq!(serde_json::from_value::<Raw>(json).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_felts_last() {
+        #[derive(Serialize)]
+        struct Nested {
+            a: u8,
+            rest: Felts,
+        }
+        let rest = Felts(felts(&[1, 2]));
+        assert_eq!(
+            to_field_elements((3u8, rest.clone())).unwrap(),
+            felts(&[3, 1, 2])
+        );
+        let err = to_field_elements((rest.clone(), 3u8)).unwrap_err();
+        assert!(matches!(err.root_cause(), EncodeError::FeltsNotLast));
+        assert_eq!(err.path().unwrap().to_string(), "[1]");
+        let nested = Nested {
+            a: 1,
+            rest: rest.clone(),
+        };
+        assert!(to_field_elements(&nested).is_ok());
+        let err = to_field_elements((nested, 4u8)).unwrap_err();
+        assert!(matches!(err.root_cause(), EncodeError::FeltsNotLast));
+        assert!(to_field_elements(vec![rest.clone(), rest]).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! Encode, decode and explain the calldata of the registered types, and call the contract.
+//! It's built with the `cli` feature, e.g. `cargo install --path . --features cli`.
+//!
+//! ```text
+//! stark-util encode --type StoredBlockInfo --json block.json
+//! stark-util decode --type StoredBlockInfo 0x1 0x2 ...
+//! stark-util view --url $URL --contract $CONTRACT getStoredBlockInfo --output StoredBlockInfo
+//! ```
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use stark_util::array::{Felt, Felts};
+use stark_util::client::StarkClient;
+use stark_util::decoder::{Decode, Decoder, DecoderImpl, SliceReader};
+use stark_util::explain::Explanation;
+use stark_util::proto::{
+    ByteArray, Bytes, CommitBlockInfo, CompressedBlockExtraInfo, ExecuteBlockInfo,
+    OnchainOperationData, StoredBlockInfo,
+};
+use stark_util::serde::Compat;
+use stark_util::{explain_field_elements, to_field_elements, Calldata, U256};
+use starknet::core::types::FieldElement;
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+use std::io::Read;
+
+/// A type which can be given as `--type`
+struct Registered {
+    name: &'static str,
+    encode: fn(Value) -> anyhow::Result<Vec<FieldElement>>,
+    decode: fn(&[FieldElement]) -> anyhow::Result<Value>,
+    explain: fn(Value) -> anyhow::Result<Explanation>,
+}
+
+macro_rules! registry {
+    ($($name:literal => $ty:ty),* $(,)?) => {
+        &[$(Registered {
+            name: $name,
+            encode: encode::<$ty>,
+            decode: decode::<$ty>,
+            explain: explain::<$ty>,
+        }),*]
+    };
+}
+
+const TYPES: &[Registered] = registry![
+    "felt252" => Felt,
+    "u8" => u8,
+    "u16" => u16,
+    "u32" => u32,
+    "u64" => u64,
+    "u128" => u128,
+    "u256" => U256,
+    "bool" => bool,
+    "Array<felt252>" => Vec<Felt>,
+    "Bytes" => Bytes,
+    "ByteArray" => ByteArray,
+    "StoredBlockInfo" => StoredBlockInfo,
+    "Array<StoredBlockInfo>" => Vec<StoredBlockInfo>,
+    "CommitBlockInfo" => CommitBlockInfo,
+    "Array<CommitBlockInfo>" => Vec<CommitBlockInfo>,
+    "OnchainOperationData" => OnchainOperationData,
+    "CompressedBlockExtraInfo" => CompressedBlockExtraInfo,
+    "Array<CompressedBlockExtraInfo>" => Vec<CompressedBlockExtraInfo>,
+    "ExecuteBlockInfo" => ExecuteBlockInfo,
+    "Array<ExecuteBlockInfo>" => Vec<ExecuteBlockInfo>,
+];
+
+fn encode<T: DeserializeOwned + Serialize>(json: Value) -> anyhow::Result<Vec<FieldElement>> {
+    let value: T = serde_json::from_value(json)?;
+    Ok(to_field_elements(&value)?)
+}
+
+fn decode<T: DeserializeOwned + Serialize>(elements: &[FieldElement]) -> anyhow::Result<Value> {
+    let mut decoder = DecoderImpl::new(SliceReader::new(elements));
+    let Compat(value) = Compat::<T>::decode(&mut decoder)?;
+    let read = decoder.field_elements_read();
+    if read != elements.len() {
+        bail!("{} trailing elements after {read}", elements.len() - read);
+    }
+    Ok(serde_json::to_value(value)?)
+}
+
+fn explain<T: DeserializeOwned + Serialize>(json: Value) -> anyhow::Result<Explanation> {
+    let value: T = serde_json::from_value(json)?;
+    Ok(explain_field_elements(&value)?)
+}
+
+fn registered(name: &str) -> anyhow::Result<&'static Registered> {
+    TYPES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names: Vec<_> = TYPES.iter().map(|t| t.name).collect();
+        anyhow!("unknown type {name}, expected one of {}", names.join(", "))
+    })
+}
+
+#[derive(Parser)]
+#[command(name = "stark-util", about = "Encode and decode starknet calldata")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encode a json value of the type, one hex element per line
+    Encode(Input),
+    /// Decode the elements into a json value of the type
+    Decode {
+        #[arg(long = "type")]
+        ty: String,
+        /// The elements, decimal or `0x` prefixed hex
+        elements: Vec<String>,
+    },
+    /// Print the field and type of each element of the encoding
+    Explain(Input),
+    /// Print the selector of a function name
+    Selector { name: String },
+    /// Invoke a function of the contract, prints the transaction hash
+    Call {
+        #[command(flatten)]
+        client: ClientArgs,
+        function: String,
+        #[command(flatten)]
+        calldata: CalldataArgs,
+    },
+    /// Call a view function of the contract, prints the decoded result or its elements
+    View {
+        #[command(flatten)]
+        client: ClientArgs,
+        function: String,
+        #[command(flatten)]
+        calldata: CalldataArgs,
+        /// The type to decode the result as
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print the receipt of a transaction as json
+    Receipt {
+        #[command(flatten)]
+        client: ClientArgs,
+        tx_hash: String,
+    },
+}
+
+/// A json value of a registered type
+#[derive(Args)]
+struct Input {
+    #[arg(long = "type")]
+    ty: String,
+    /// The json file, `-` for stdin
+    #[arg(long)]
+    json: String,
+}
+
+impl Input {
+    fn read(&self) -> anyhow::Result<Value> {
+        let json = if self.json == "-" {
+            let mut json = String::new();
+            std::io::stdin().read_to_string(&mut json)?;
+            json
+        } else {
+            std::fs::read_to_string(&self.json).with_context(|| format!("read {}", self.json))?
+        };
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// The calldata of a function, a json value of a registered type or the raw elements
+#[derive(Args)]
+struct CalldataArgs {
+    #[arg(long = "type", requires = "json")]
+    ty: Option<String>,
+    /// The json file of the `--type` value, `-` for stdin
+    #[arg(long, requires = "ty")]
+    json: Option<String>,
+    /// The raw elements, when there is no `--type`
+    #[arg(conflicts_with = "ty")]
+    elements: Vec<String>,
+}
+
+impl CalldataArgs {
+    fn elements(&self) -> anyhow::Result<Vec<FieldElement>> {
+        match (&self.ty, &self.json) {
+            (Some(ty), Some(json)) => {
+                let value = Input {
+                    ty: ty.clone(),
+                    json: json.clone(),
+                };
+                (registered(ty)?.encode)(value.read()?)
+            }
+            _ => parse_elements(&self.elements),
+        }
+    }
+}
+
+#[derive(Args)]
+struct ClientArgs {
+    /// The url of the sequencer gateway
+    #[arg(long, env = "STARK_URL")]
+    url: String,
+    /// The key of the account, only needed to invoke functions
+    #[arg(long, env = "STARK_PRIVATE_KEY", hide_env_values = true)]
+    private_key: Option<String>,
+    /// The account address, only needed to invoke functions
+    #[arg(long, env = "STARK_ADDRESS")]
+    address: Option<String>,
+    #[arg(long, env = "STARK_CONTRACT")]
+    contract: String,
+    /// The chain id, hex or a short string like `SN_GOERLI`
+    #[arg(long, env = "STARK_CHAIN_ID", default_value = "SN_GOERLI")]
+    chain_id: String,
+}
+
+impl ClientArgs {
+    /// The client of the account, which signs the invocations
+    fn account(&self) -> anyhow::Result<StarkClient> {
+        let (Some(private_key), Some(address)) = (&self.private_key, &self.address) else {
+            bail!("--private-key and --address are required to invoke a function");
+        };
+        self.client(private_key, address)
+    }
+
+    /// A client without account for the read-only requests
+    fn reader(&self) -> anyhow::Result<StarkClient> {
+        Ok(StarkClient::reader(
+            &self.url,
+            &self.contract,
+            self.chain_id()?,
+        ))
+    }
+
+    fn client(&self, private_key: &str, address: &str) -> anyhow::Result<StarkClient> {
+        Ok(StarkClient::new(
+            &self.url,
+            private_key,
+            address,
+            &self.contract,
+            self.chain_id()?,
+        ))
+    }
+
+    fn chain_id(&self) -> anyhow::Result<FieldElement> {
+        if self.chain_id.starts_with("0x") {
+            Ok(FieldElement::from_hex_be(&self.chain_id)?)
+        } else {
+            Ok(cairo_short_string_to_felt(&self.chain_id)?)
+        }
+    }
+}
+
+/// Parse the elements of the arguments, which may also be comma separated
+fn parse_elements(elements: &[String]) -> anyhow::Result<Vec<FieldElement>> {
+    Ok(elements.join(" ").parse::<Calldata>()?.0)
+}
+
+fn print_elements(elements: &[FieldElement]) {
+    for element in elements {
+        println!("{element:#x}");
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Encode(value) => {
+            let elements = (registered(&value.ty)?.encode)(value.read()?)?;
+            print_elements(&elements);
+        }
+        Command::Decode { ty, elements } => {
+            let value = (registered(&ty)?.decode)(&parse_elements(&elements)?)?;
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        Command::Explain(value) => {
+            let explanation = (registered(&value.ty)?.explain)(value.read()?)?;
+            print!("{explanation}");
+        }
+        Command::Selector { name } => {
+            println!("{:#x}", get_selector_from_name(&name)?);
+        }
+        Command::Call {
+            client,
+            function,
+            calldata,
+        } => {
+            let tx_hash = client
+                .account()?
+                .call(&function, Felts(calldata.elements()?))
+                .await?;
+            println!("0x{}", String::from(tx_hash));
+        }
+        Command::View {
+            client,
+            function,
+            calldata,
+            output,
+        } => {
+            let result = client
+                .reader()?
+                .view(&function, Felts(calldata.elements()?))
+                .await?;
+            match output {
+                Some(ty) => {
+                    let value = (registered(&ty)?.decode)(&result)?;
+                    println!("{}", serde_json::to_string_pretty(&value)?);
+                }
+                None => print_elements(&result),
+            }
+        }
+        Command::Receipt { client, tx_hash } => {
+            let tx_hash = FieldElement::from_hex_be(&tx_hash)
+                .with_context(|| format!("invalid transaction hash {tx_hash}"))?;
+            let receipt = client
+                .reader()?
+                .get_transaction_receipt(tx_hash.into())
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&receipt)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn felts(values: &[u64]) -> Vec<FieldElement> {
+        values.iter().map(|v| FieldElement::from(*v)).collect()
+    }
+
+    #[test]
+    fn test_registered() {
+        let ty = registered("StoredBlockInfo").unwrap();
+        assert_eq!(ty.name, "StoredBlockInfo");
+        let json = serde_json::to_value(StoredBlockInfo::default()).unwrap();
+        let elements = (ty.encode)(json.clone()).unwrap();
+        assert_eq!(elements.len(), 11);
+        assert_eq!((ty.decode)(&elements).unwrap(), json);
+
+        let error = registered("StoredBlock").err().unwrap().to_string();
+        assert!(error.starts_with("unknown type StoredBlock, expected one of felt252, u8"));
+    }
+
+    #[test]
+    fn test_decode_trailing_elements() {
+        assert_eq!(decode::<u64>(&felts(&[7])).unwrap(), 7);
+        let error = decode::<u64>(&felts(&[7, 8, 9])).unwrap_err();
+        assert_eq!(error.to_string(), "2 trailing elements after 1");
+        assert!(decode::<U256>(&felts(&[1])).is_err());
+    }
+
+    #[test]
+    fn test_calldata_elements() {
+        let raw = CalldataArgs {
+            ty: None,
+            json: None,
+            elements: vec!["0x1,2".to_string(), "3".to_string()],
+        };
+        assert_eq!(raw.elements().unwrap(), felts(&[1, 2, 3]));
+
+        let path = std::env::temp_dir().join("stark-util-test-calldata.json");
+        std::fs::write(&path, r#""0x600000000000000000000000000000005""#).unwrap();
+        let typed = CalldataArgs {
+            ty: Some("u256".to_string()),
+            json: Some(path.to_string_lossy().into_owned()),
+            elements: vec![],
+        };
+        assert_eq!(typed.elements().unwrap(), felts(&[5, 6]));
+        std::fs::remove_file(&path).unwrap();
+
+        let unknown = CalldataArgs {
+            ty: Some("u7".to_string()),
+            ..typed
+        };
+        assert!(unknown.elements().is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! The text formats of calldata: the lists of `0x` prefixed hex strings of the json-rpc nodes, and
+//! the space or comma separated numbers typed in a shell.
+
+use crate::error::{CalldataError, EncodeError};
+use crate::{from_slice, to_field_elements};
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::types::{FieldElement, FromStrError};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The elements of a calldata, displayed and serialized as `0x` prefixed hex strings.
+///
+/// It's parsed from decimal or hex numbers separated by spaces or commas, optionally in brackets,
+/// so the json array of a node can be pasted as is. Deserialization accepts strings of both
+/// bases as well.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Calldata(pub Vec<FieldElement>);
+
+impl Calldata {
+    /// The elements of `value`, like [crate::to_field_elements].
+    pub fn encode<T: Serialize>(value: T) -> Result<Self, EncodeError> {
+        to_field_elements(value).map(Self)
+    }
+
+    /// Decode `T` from the elements, like [crate::from_slice].
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, CalldataError> {
+        Ok(from_slice(&self.0)?)
+    }
+
+    /// The `0x` prefixed hex strings of the elements
+    pub fn to_hex_strings(&self) -> Vec<String> {
+        self.0.iter().map(|e| format!("{e:#x}")).collect()
+    }
+
+    /// Parse the decimal or `0x` prefixed hex strings, the errors have the index of the string.
+    pub fn from_strings<S: AsRef<str>>(strings: &[S]) -> Result<Self, CalldataError> {
+        strings
+            .iter()
+            .enumerate()
+            .map(|(index, token)| parse_element(index, token.as_ref()))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl From<Vec<FieldElement>> for Calldata {
+    fn from(value: Vec<FieldElement>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Calldata> for Vec<FieldElement> {
+    fn from(value: Calldata) -> Self {
+        value.0
+    }
+}
+
+/// Space separated hex strings, the format of starkli
+impl Display for Calldata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, element) in self.0.iter().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            write!(f, "{sep}{element:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse decimal or hex numbers separated by spaces or commas, e.g. `[0x1, 2]` or `0x1 2`
+impl FromStr for Calldata {
+    type Err = CalldataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .unwrap_or(s);
+        let tokens: Vec<_> = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.trim_matches('"'))
+            .collect();
+        Self::from_strings(&tokens)
+    }
+}
+
+impl Serialize for Calldata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|e| format!("{e:#x}")))
+    }
+}
+
+impl<'de> Deserialize<'de> for Calldata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let strings = Vec::<String>::deserialize(deserializer)?;
+        Self::from_strings(&strings).map_err(de::Error::custom)
+    }
+}
+
+/// Parse the token at `index` of a calldata.
+fn parse_element(index: usize, token: &str) -> Result<FieldElement, CalldataError> {
+    let invalid = || CalldataError::InvalidElement {
+        index,
+        token: token.to_string(),
+    };
+    // the parsers of `FieldElement` take an empty string for zero
+    let parsed = match token.strip_prefix("0x").or(token.strip_prefix("0X")) {
+        Some("") => return Err(invalid()),
+        Some(digits) => FieldElement::from_hex_be(digits),
+        None if token.is_empty() => return Err(invalid()),
+        None => FieldElement::from_dec_str(token),
+    };
+    parsed.map_err(|e| match e {
+        FromStrError::InvalidCharacter => invalid(),
+        FromStrError::OutOfRange => CalldataError::OutOfRange {
+            index,
+            token: token.to_string(),
+        },
+    })
+}
+
+/// Encode `value` as `0x` prefixed hex strings, the calldata format of the json-rpc nodes.
+pub fn to_hex_strings<T: Serialize>(value: T) -> Result<Vec<String>, EncodeError> {
+    Ok(Calldata::encode(value)?.to_hex_strings())
+}
+
+/// Decode `T` from decimal or `0x` prefixed hex strings.
+pub fn from_hex_strings<T, S>(strings: &[S]) -> Result<T, CalldataError>
+where
+    T: DeserializeOwned,
+    S: AsRef<str>,
+{
+    Calldata::from_strings(strings)?.decode()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::StoredBlockInfo;
+    use crate::U256;
+
+    fn stored_block_info() -> StoredBlockInfo {
+        StoredBlockInfo {
+            block_number: 10,
+            priority_operations: 2,
+            pending_onchain_operations_hash: U256::from(3u8),
+            timestamp: 1700000000,
+            state_hash: U256::new(4, 5),
+            commitment: U256::from(6u8),
+            sync_hash: U256::from(7u8),
+        }
+    }
+
+    #[test]
+    fn test_hex_strings() {
+        let info = stored_block_info();
+        let strings = to_hex_strings(&info).unwrap();
+        assert_eq!(strings[..4], ["0xa", "0x2", "0x3", "0x0"]);
+        assert_eq!(
+            from_hex_strings::<StoredBlockInfo, _>(&strings).unwrap(),
+            info
+        );
+
+        let calldata: Calldata = "[0x1, 2,\n 0X3 \"0x4\"]".parse().unwrap();
+        assert_eq!(calldata.0, [1u8, 2, 3, 4].map(FieldElement::from));
+        assert_eq!(calldata.to_string(), "0x1 0x2 0x3 0x4");
+        assert_eq!(calldata.to_string().parse::<Calldata>().unwrap(), calldata);
+        assert_eq!("".parse::<Calldata>().unwrap(), Calldata::default());
+
+        let json = serde_json::to_string(&calldata).unwrap();
+        assert_eq!(json, r#"["0x1","0x2","0x3","0x4"]"#);
+        let decimal: Calldata = serde_json::from_str(r#"["1","0x2","3","4"]"#).unwrap();
+        assert_eq!(decimal, calldata);
+    }
+
+    #[test]
+    fn test_invalid_calldata() {
+        let error = "0x1 0x 3".parse::<Calldata>().unwrap_err();
+        assert_eq!(error.to_string(), r#"invalid element "0x" at index 1"#);
+        assert!(matches!(
+            "1,2,-3".parse::<Calldata>(),
+            Err(CalldataError::InvalidElement { index: 2, .. })
+        ));
+        assert!(matches!(
+            "0xg".parse::<Calldata>(),
+            Err(CalldataError::InvalidElement { index: 0, .. })
+        ));
+        let modulus = "0x800000000000011000000000000000000000000000000000000000000000001";
+        assert!(matches!(
+            Calldata::from_strings(&["0x0", modulus]),
+            Err(CalldataError::OutOfRange { index: 1, .. })
+        ));
+        assert!(serde_json::from_str::<Calldata>(r#"["1", "x"]"#)
+            .unwrap_err()
+            .to_string()
+            .contains("index 1"));
+        assert!(matches!(
+            from_hex_strings::<StoredBlockInfo, _>(&["0x1"]),
+            Err(CalldataError::Decode(_))
+        ));
+    }
+}
@@ -1,19 +1,33 @@
-use std::sync::Arc;
+use crate::abi::Abi;
 use crate::proto::TxHash;
 use crate::to_field_elements;
 use serde::Serialize;
 use starknet::accounts::{Account, Call, ConnectedAccount, SingleOwnerAccount};
-use starknet::core::types::{BlockId, BlockTag, BroadcastedDeclareTransaction, BroadcastedDeclareTransactionV1, BroadcastedDeclareTransactionV2, BroadcastedDeployAccountTransaction, BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1, CompressedLegacyContractClass, FieldElement };
+use starknet::core::types::{
+    BlockId, BlockTag, BroadcastedDeclareTransaction, BroadcastedDeclareTransactionV1,
+    BroadcastedDeclareTransactionV2, BroadcastedDeployAccountTransaction,
+    BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1, CompressedLegacyContractClass,
+    ContractClass, EmittedEvent, EventFilter, EventsPage, FieldElement, FunctionCall,
+    MaybePendingTransactionReceipt,
+};
 use starknet::core::utils::get_selector_from_name;
+use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
 use starknet::providers::sequencer::models::{TransactionRequest, DeclareTransactionRequest};
 use starknet::providers::{Provider, SequencerGatewayProvider};
 use starknet::signers::{LocalWallet, SigningKey};
+use std::future::Future;
+use std::sync::Arc;
 
 pub struct StarkClient {
-    inner: SingleOwnerAccount<SequencerGatewayProvider, LocalWallet>,
+    /// The account which signs the invocations, `None` for a client of [StarkClient::reader]
+    inner: Option<SingleOwnerAccount<SequencerGatewayProvider, LocalWallet>>,
+    /// The sequencer gateway of the read requests
+    provider: SequencerGatewayProvider,
+    /// The json-rpc node at `{web3_url}/rpc`, the sequencer gateway doesn't serve events
+    rpc: JsonRpcClient<HttpTransport>,
     pub contract_address: FieldElement,
-    pub address: FieldElement,
-    pub local_wallet: LocalWallet,
+    pub address: Option<FieldElement>,
+    pub local_wallet: Option<LocalWallet>,
 }
 
 impl StarkClient {
@@ -24,35 +38,50 @@ impl StarkClient {
         contract_address: &str,
         chain_id: FieldElement,
     ) -> Self {
-        let gateway_url: url::Url = format!("{}/gateway", web3_url).parse().unwrap();
-        let feeder_gateway_url: url::Url = format!("{}/feeder_gateway", web3_url).parse().unwrap();
-        let provider = SequencerGatewayProvider::new(gateway_url, feeder_gateway_url, chain_id);
         let signer = LocalWallet::from(SigningKey::from_secret_scalar(
             FieldElement::from_hex_be(private_key_hex).unwrap(),
         ));
         let address = FieldElement::from_hex_be(address).unwrap();
-        let contract_address = FieldElement::from_hex_be(contract_address).unwrap();
+        let provider = gateway_provider(web3_url, chain_id);
         let account = SingleOwnerAccount::new(provider, signer.clone(), address, chain_id);
         Self {
-            inner: account,
-            contract_address,
-            address,
-            local_wallet: signer,
+            inner: Some(account),
+            address: Some(address),
+            local_wallet: Some(signer),
+            ..Self::reader(web3_url, contract_address, chain_id)
         }
     }
 
-    pub fn client(&self) -> &SingleOwnerAccount<SequencerGatewayProvider, LocalWallet> {
-        &self.inner
+    /// A client without account for the read requests, which can't invoke functions.
+    pub fn reader(web3_url: &str, contract_address: &str, chain_id: FieldElement) -> Self {
+        let rpc_url: url::Url = format!("{}/rpc", web3_url).parse().unwrap();
+        Self {
+            inner: None,
+            provider: gateway_provider(web3_url, chain_id),
+            rpc: JsonRpcClient::new(HttpTransport::new(rpc_url)),
+            contract_address: FieldElement::from_hex_be(contract_address).unwrap(),
+            address: None,
+            local_wallet: None,
+        }
+    }
+
+    /// The account of the client, an error for a client of [StarkClient::reader].
+    pub fn client(
+        &self,
+    ) -> anyhow::Result<&SingleOwnerAccount<SequencerGatewayProvider, LocalWallet>> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("the client has no account to sign with"))
     }
 
     pub async fn get_last_block_number(&self) -> anyhow::Result<u64> {
-        let number = self.client().provider().block_number().await?;
+        let number = self.provider.block_number().await?;
         Ok(number)
     }
 
     pub async fn get_pending_nonce(&self) -> anyhow::Result<FieldElement> {
         let nonce = Provider::get_nonce(
-            self.client().provider(),
+            &self.provider,
             BlockId::Tag(BlockTag::Pending),
             self.contract_address,
         )
@@ -60,6 +89,50 @@ impl StarkClient {
         Ok(nonce)
     }
 
+    /// Get the ABI of the deployed contract, to check the calldata types with [Abi::check_inputs].
+    pub async fn get_abi(&self) -> anyhow::Result<Abi> {
+        let class = self
+            .provider
+            .get_class_at(BlockId::Tag(BlockTag::Latest), self.contract_address)
+            .await?;
+        match class {
+            ContractClass::Sierra(class) => Ok(Abi::from_json(&class.abi)?),
+            ContractClass::Legacy(_) => anyhow::bail!("the contract is not a cairo 1 contract"),
+        }
+    }
+
+    /// Get the events emitted by the contract between `from_block` and `to_block`(inclusive) from
+    /// the json-rpc node, all the pages of the continuation token are fetched with `chunk_size`
+    /// events per request.
+    ///
+    /// `keys` filters the events by keys, the i-th list contains the accepted values of the i-th key,
+    /// an empty `keys` matches all events.
+    pub async fn get_events(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        keys: Vec<Vec<FieldElement>>,
+        chunk_size: u64,
+    ) -> anyhow::Result<Vec<EmittedEvent>> {
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(from_block)),
+            to_block: Some(BlockId::Number(to_block)),
+            address: Some(self.contract_address),
+            keys: if keys.is_empty() { None } else { Some(keys) },
+        };
+        fetch_all_events(|continuation_token| {
+            let filter = filter.clone();
+            async move {
+                let page = self
+                    .rpc
+                    .get_events(filter, continuation_token, chunk_size)
+                    .await?;
+                Ok(page)
+            }
+        })
+        .await
+    }
+
     pub async fn send_transaction(
         &self,
         request: TransactionRequest,
@@ -84,7 +157,7 @@ impl StarkClient {
                             sender_address: tx.sender_address,
                         };
                         let tx = BroadcastedDeclareTransaction::V1(t);
-                        let result = self.client().provider().add_declare_transaction(tx).await?;
+                        let result = self.provider.add_declare_transaction(tx).await?;
                         result.transaction_hash
                     },
                     DeclareTransactionRequest::V2(tx) => {
@@ -98,7 +171,7 @@ impl StarkClient {
                             compiled_class_hash: tx.compiled_class_hash,
                         };
                         let tx = BroadcastedDeclareTransaction::V2(t);
-                        let result = self.client().provider().add_declare_transaction(tx).await?;
+                        let result = self.provider.add_declare_transaction(tx).await?;
                         result.transaction_hash
                     }
                 }
@@ -112,11 +185,7 @@ impl StarkClient {
                     signature: value.signature,
                     nonce: value.nonce
                 };
-                let r = self
-                    .client()
-                    .provider()
-                    .add_deploy_account_transaction(tx)
-                    .await?;
+                let r = self.provider.add_deploy_account_transaction(tx).await?;
                 r.transaction_hash
             }
             TransactionRequest::InvokeFunction(value) => {
@@ -128,32 +197,38 @@ impl StarkClient {
                     calldata: value.calldata
                 };
                 let tx = BroadcastedInvokeTransaction::V1(tx_v1);
-                let r = self.client().provider().add_invoke_transaction(tx).await?;
+                let r = self.provider.add_invoke_transaction(tx).await?;
                 r.transaction_hash
             }
         };
         Ok(tx_hash)
     }
 
-    // pub async fn get_transaction_receipt(&self, tx_hash: TxHash) -> anyhow::Result<FieldElement> {
-    //     let r = Provider::get_transaction_receipt(self.client().provider(), &tx_hash).await?;
-    //     match r {
-    //         MaybePendingTransactionReceipt::Receipt(receipt) => {
-    //             match receipt {
-    //                 TransactionReceipt::DeployAccount(recipt) => {
-    //                     let s = recipt.status
-    //                     Ok(receipt.transaction_hash)
-    //                 },
-    //                 TransactionReceipt::Declare(d) => Ok(d.transaction_hash)
-    //                 TransactionReceipt::
-    //             }
-    //         },
-    //         MaybePendingTransactionReceipt::PendingReceipt(receipt) => {
-    //
-    //         }
-    //     }
-    //
-    // }
+    /// Get the receipt of `tx_hash`, which is pending until the block of the transaction is closed.
+    pub async fn get_transaction_receipt(
+        &self,
+        tx_hash: TxHash,
+    ) -> anyhow::Result<MaybePendingTransactionReceipt> {
+        let receipt = Provider::get_transaction_receipt(&self.provider, tx_hash).await?;
+        Ok(receipt)
+    }
+
+    /// Call the view function `func_name` at the latest block, returns the elements of the result.
+    pub async fn view<T>(&self, func_name: &str, calldata: T) -> anyhow::Result<Vec<FieldElement>>
+    where
+        T: Serialize,
+    {
+        let request = FunctionCall {
+            contract_address: self.contract_address,
+            entry_point_selector: get_selector_from_name(func_name)?,
+            calldata: to_field_elements(calldata)?,
+        };
+        let result = self
+            .provider
+            .call(request, BlockId::Tag(BlockTag::Latest))
+            .await?;
+        Ok(result)
+    }
 
     pub async fn call<T>(&self, func_name: &str, calldata: T) -> anyhow::Result<TxHash>
     where
@@ -162,7 +237,7 @@ impl StarkClient {
         let selector = get_selector_from_name(func_name).unwrap();
         let calldata_elements = to_field_elements(calldata)?;
         let result = self
-            .client()
+            .client()?
             .execute(vec![Call {
                 to: self.contract_address,
                 selector,
@@ -175,16 +250,46 @@ impl StarkClient {
     }
 }
 
+/// The sequencer gateway at `{web3_url}/gateway` and `{web3_url}/feeder_gateway`.
+fn gateway_provider(web3_url: &str, chain_id: FieldElement) -> SequencerGatewayProvider {
+    let gateway_url: url::Url = format!("{}/gateway", web3_url).parse().unwrap();
+    let feeder_gateway_url: url::Url = format!("{}/feeder_gateway", web3_url).parse().unwrap();
+    SequencerGatewayProvider::new(gateway_url, feeder_gateway_url, chain_id)
+}
+
+/// Fetch the pages of events until there is no continuation token, `fetch` gets the token of the
+/// previous page.
+async fn fetch_all_events<F, Fut>(mut fetch: F) -> anyhow::Result<Vec<EmittedEvent>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = anyhow::Result<EventsPage>>,
+{
+    let mut events = vec![];
+    let mut continuation_token = None;
+    loop {
+        let page = fetch(continuation_token).await?;
+        events.extend(page.events);
+        continuation_token = page.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    Ok(events)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::client::StarkClient;
+    use crate::array::Felt;
+    use crate::client::{fetch_all_events, StarkClient};
+    use crate::event::ZkLinkEvent;
     use crate::num::PrimitiveU256;
     use crate::proto::{
         Bytes, CommitBlockInfo, CompressedBlockExtraInfo, ExecuteBlockInfo, OnchainOperationData,
         StoredBlockInfo, TxHash,
     };
-    use crate::U256;
+    use crate::{from_slice, hash, U256};
     use starknet::core::chain_id;
+    use starknet::core::types::{EmittedEvent, EventsPage, FieldElement};
 
     impl StarkClient {
         pub async fn test_u128_list(
@@ -262,6 +367,42 @@ mod test {
             self.call("ExecuteBlockInfoTest2", (info_list, i, j, op_type))
                 .await
         }
+
+        pub async fn test_hash_stored_block_info(
+            &self,
+            info: &StoredBlockInfo,
+        ) -> anyhow::Result<U256> {
+            let result = self.view("hashStoredBlockInfoTest", info).await?;
+            Ok(from_slice(&result)?)
+        }
+
+        pub async fn test_hash_stored_block_info_pedersen(
+            &self,
+            info: &StoredBlockInfo,
+        ) -> anyhow::Result<Felt> {
+            let result = self.view("hashStoredBlockInfoPedersenTest", info).await?;
+            Ok(from_slice(&result)?)
+        }
+
+        pub async fn test_hash_stored_block_info_poseidon(
+            &self,
+            info: &StoredBlockInfo,
+        ) -> anyhow::Result<Felt> {
+            let result = self.view("hashStoredBlockInfoPoseidonTest", info).await?;
+            Ok(from_slice(&result)?)
+        }
+
+        /// The [StoredBlockInfo] computed by the contract when it commits `block`.
+        pub async fn test_commit_block(
+            &self,
+            previous_block: &StoredBlockInfo,
+            block: &CommitBlockInfo,
+        ) -> anyhow::Result<StoredBlockInfo> {
+            let result = self
+                .view("commitBlockTest", (previous_block, block))
+                .await?;
+            Ok(from_slice(&result)?)
+        }
     }
 
     fn client() -> StarkClient {
@@ -280,6 +421,83 @@ mod test {
         client
     }
 
+    fn emitted_event(block_number: u64) -> EmittedEvent {
+        EmittedEvent {
+            from_address: FieldElement::ONE,
+            keys: vec![],
+            data: vec![],
+            block_hash: FieldElement::ZERO,
+            block_number,
+            transaction_hash: FieldElement::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_events() {
+        // three pages, the token of a page is the number of the next one
+        let mut tokens = vec![];
+        let events = fetch_all_events(|token| {
+            tokens.push(token.clone());
+            let page = token.map_or(0, |t| t.parse().unwrap());
+            async move {
+                Ok(EventsPage {
+                    events: vec![emitted_event(page * 2), emitted_event(page * 2 + 1)],
+                    continuation_token: (page < 2).then(|| (page + 1).to_string()),
+                })
+            }
+        })
+        .await
+        .unwrap();
+        let numbers: Vec<_> = events.iter().map(|e| e.block_number).collect();
+        assert_eq!(numbers, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(tokens, [None, Some("1".into()), Some("2".into())]);
+
+        // an empty page with a token is followed
+        let mut calls = 0;
+        let events = fetch_all_events(|_| {
+            calls += 1;
+            let last = calls == 2;
+            async move {
+                Ok(EventsPage {
+                    events: if last { vec![emitted_event(7)] } else { vec![] },
+                    continuation_token: (!last).then(|| "next".to_string()),
+                })
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(events.len(), 1);
+
+        // the error of a page is returned
+        let error = fetch_all_events(|token| async move {
+            match token {
+                None => Ok(EventsPage {
+                    events: vec![emitted_event(0)],
+                    continuation_token: Some("1".to_string()),
+                }),
+                Some(_) => Err(anyhow::anyhow!("node down")),
+            }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(error.to_string(), "node down");
+    }
+
+    #[tokio::test]
+    async fn test_get_events() {
+        let client = client();
+        let to_block = client.get_last_block_number().await.unwrap();
+        let events = client
+            .get_events(0, to_block, ZkLinkEvent::keys(), 10)
+            .await
+            .unwrap();
+        for event in events {
+            let e = ZkLinkEvent::from_emitted_event(&event);
+            println!("{e:?}");
+            assert!(matches!(e, Ok(Some(_))));
+        }
+    }
+
     #[tokio::test]
     async fn test_u128_list() {
         let r = client().test_u128_list(vec![1u128], 0).await;
@@ -404,4 +622,27 @@ mod test {
         println!("{r:?}");
         assert!(r.is_ok());
     }
+
+    // The vectors of `hash::test` must match the contract, this checks them against a devnet.
+    #[tokio::test]
+    async fn test_hash_stored_block_info() {
+        let info = hash::test::stored_block_info();
+        let client = client();
+        let r = client.test_hash_stored_block_info(&info).await.unwrap();
+        assert_eq!(r, hash::hash_stored_block_info(&info));
+        let r = client
+            .test_hash_stored_block_info_pedersen(&info)
+            .await
+            .unwrap();
+        assert_eq!(r.0, hash::pedersen_hash_stored_block_info(&info));
+        let r = client
+            .test_hash_stored_block_info_poseidon(&info)
+            .await
+            .unwrap();
+        assert_eq!(r.0, hash::poseidon_hash_stored_block_info(&info));
+
+        let block = hash::test::commit_block_info();
+        let r = client.test_commit_block(&info, &block).await.unwrap();
+        assert_eq!(r, hash::commit_block(&info, &block).unwrap());
+    }
 }
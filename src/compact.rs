@@ -0,0 +1,228 @@
+//! A compact binary container of encoded elements, to persist calldata before it's submitted.
+//!
+//! | size   | field                                           |
+//! |--------|-------------------------------------------------|
+//! | 4      | the magic bytes `SFLT`                          |
+//! | 1      | the version, [VERSION]                          |
+//! | varint | the length of the body                          |
+//! | 8      | the first 8 bytes of the keccak256 of the body  |
+//! | body   | the varint count of elements, then each element |
+//!
+//! Most elements are small numbers, so an element is its varint length then its big-endian bytes
+//! without the leading zeros. The varints are LEB128. A container is read exactly, so containers
+//! can be appended to a file.
+
+use crate::error::CompactError;
+use sha3::{Digest, Keccak256};
+use starknet::core::types::FieldElement;
+use std::io::{Read, Write};
+
+const MAGIC: [u8; 4] = *b"SFLT";
+
+/// The version written by [write_to], the only one [read_from] supports
+pub const VERSION: u8 = 1;
+
+const CHECKSUM_BYTES: usize = 8;
+
+fn checksum(body: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    let digest = Keccak256::digest(body);
+    digest[..CHECKSUM_BYTES].try_into().unwrap()
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, CompactError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        let bits = u64::from(byte[0] & 0x7f);
+        if shift == 63 && bits > 1 {
+            break;
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(CompactError::Invalid("varint overflow"))
+}
+
+/// Write the container of `elements`.
+pub fn write_to<W: Write>(elements: &[FieldElement], mut writer: W) -> Result<(), CompactError> {
+    let mut body = vec![];
+    write_varint(&mut body, elements.len() as u64);
+    for element in elements {
+        let bytes = element.to_bytes_be();
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        write_varint(&mut body, (bytes.len() - zeros) as u64);
+        body.extend_from_slice(&bytes[zeros..]);
+    }
+
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+    write_varint(&mut header, body.len() as u64);
+    header.extend_from_slice(&checksum(&body));
+    writer.write_all(&header)?;
+    writer.write_all(&body)?;
+    Ok(())
+}
+
+/// Read a container written by [write_to], the reader is left after its last byte.
+pub fn read_from<R: Read>(mut reader: R) -> Result<Vec<FieldElement>, CompactError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(CompactError::InvalidMagic);
+    }
+    let mut version = [0u8];
+    reader.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(CompactError::UnsupportedVersion(version[0]));
+    }
+    let len = read_varint(&mut reader)?;
+    let mut expected = [0u8; CHECKSUM_BYTES];
+    reader.read_exact(&mut expected)?;
+
+    // a corrupted length must not allocate more than the reader has
+    let mut body = vec![];
+    reader.by_ref().take(len).read_to_end(&mut body)?;
+    if (body.len() as u64) < len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    if checksum(&body) != expected {
+        return Err(CompactError::ChecksumMismatch);
+    }
+
+    let mut body = body.as_slice();
+    let count = read_varint(&mut body)?;
+    let mut elements = Vec::with_capacity(count.min(body.len() as u64) as usize);
+    for _ in 0..count {
+        let len = read_varint(&mut body)? as usize;
+        if len > 32 {
+            return Err(CompactError::Invalid("element longer than 32 bytes"));
+        }
+        let mut bytes = [0u8; 32];
+        body.read_exact(&mut bytes[32 - len..])?;
+        if len > 0 && bytes[32 - len] == 0 {
+            return Err(CompactError::Invalid("leading zero byte"));
+        }
+        let element = FieldElement::from_bytes_be(&bytes)
+            .map_err(|_| CompactError::Invalid("element out of range"))?;
+        elements.push(element);
+    }
+    if !body.is_empty() {
+        return Err(CompactError::Invalid("trailing bytes"));
+    }
+    Ok(elements)
+}
+
+/// The container of `elements` in a buffer.
+pub fn to_compact_bytes(elements: &[FieldElement]) -> Vec<u8> {
+    let mut buf = vec![];
+    write_to(elements, &mut buf).expect("writing to a vec");
+    buf
+}
+
+/// Read the container in `bytes`, which must not have trailing bytes.
+pub fn from_compact_bytes(mut bytes: &[u8]) -> Result<Vec<FieldElement>, CompactError> {
+    let elements = read_from(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(CompactError::Invalid("trailing bytes"));
+    }
+    Ok(elements)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::{Bytes, CommitBlockInfo, OnchainOperationData};
+    use crate::{to_field_elements, U256};
+
+    fn elements() -> Vec<FieldElement> {
+        let info = CommitBlockInfo {
+            new_state_hash: U256::new(u128::MAX, 1),
+            public_data: Bytes::from_bytes(&[7; 40]),
+            timestamp: 1700000000,
+            onchain_operations: vec![OnchainOperationData {
+                eth_witness: Bytes::default(),
+                public_data_offset: 0,
+            }],
+            block_number: 300,
+            fee_account: 3,
+        };
+        let mut elements = to_field_elements(&info).unwrap();
+        elements.push(FieldElement::MAX);
+        elements
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let elements = elements();
+        let bytes = to_compact_bytes(&elements);
+        // less than a third of the raw 32 bytes elements
+        assert!(bytes.len() * 3 < elements.len() * 32);
+        assert_eq!(&bytes[..5], b"SFLT\x01");
+        assert_eq!(from_compact_bytes(&bytes).unwrap(), elements);
+
+        assert_eq!(from_compact_bytes(&to_compact_bytes(&[])).unwrap(), []);
+        assert_eq!(
+            to_compact_bytes(&[FieldElement::ZERO, FieldElement::from(300u16)])[14..],
+            [2, 0, 2, 1, 44]
+        );
+
+        // containers appended to a stream
+        let mut stream = vec![];
+        write_to(&elements, &mut stream).unwrap();
+        write_to(&elements[..3], &mut stream).unwrap();
+        let mut reader = stream.as_slice();
+        assert_eq!(read_from(&mut reader).unwrap(), elements);
+        assert_eq!(read_from(&mut reader).unwrap(), elements[..3]);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_compact_corruption() {
+        let bytes = to_compact_bytes(&elements());
+
+        for i in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 0x10;
+            assert!(from_compact_bytes(&corrupted).is_err(), "byte {i}");
+        }
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert!(matches!(
+            from_compact_bytes(&corrupted),
+            Err(CompactError::InvalidMagic)
+        ));
+        corrupted = bytes.clone();
+        corrupted[4] = 2;
+        assert!(matches!(
+            from_compact_bytes(&corrupted),
+            Err(CompactError::UnsupportedVersion(2))
+        ));
+        corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            from_compact_bytes(&corrupted),
+            Err(CompactError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            from_compact_bytes(&bytes[..bytes.len() - 1]),
+            Err(CompactError::Io(_))
+        ));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            from_compact_bytes(&trailing),
+            Err(CompactError::Invalid("trailing bytes"))
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+/// How the keys of maps and sets are checked, their entries are always sorted by the encoding of
+/// their keys so the encoding of a `HashMap` doesn't depend on its iteration order.
+///
+/// Serde serializes sets like a `Vec`, so a `HashSet` field is only sorted with
+/// [crate::sorted_set], the native [crate::encoder::Encode] of sets is always sorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapOrder {
+    /// Different keys with the same encoding are rejected.
+    #[default]
+    Sorted,
+    /// Keys must also have encodings of the same length, so the order of the entries is the order
+    /// of the numbers of their key elements, e.g. the keys of different lengths of
+    /// `HashMap<Vec<u8>, _>` are rejected.
+    Strict,
+}
+
+/// How booleans are decoded, they are always encoded as `0` or `1`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolMode {
+    /// Only `0` and `1` are valid, like the `Serde` of cairo 1
+    #[default]
+    Strict,
+    /// Any non zero element is `true`
+    NonZero,
+}
+
+/// The options of the encoding and decoding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub map_order: MapOrder,
+    pub bool_mode: BoolMode,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Self {
+            map_order: MapOrder::Sorted,
+            bool_mode: BoolMode::Strict,
+        }
+    }
+
+    pub const fn with_map_order(mut self, map_order: MapOrder) -> Self {
+        self.map_order = map_order;
+        self
+    }
+
+    pub const fn with_bool_mode(mut self, bool_mode: BoolMode) -> Self {
+        self.bool_mode = bool_mode;
+        self
+    }
+}
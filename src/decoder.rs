@@ -1,13 +1,22 @@
-use crate::der::reader::Reader;
+use crate::config::{BoolMode, Config};
+use crate::encoder::{OPTION_SOME, RESULT_OK};
 use crate::error::DecodeError;
 use starknet::core::types::FieldElement;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
 
-pub fn u128_from_field_element(field_element: FieldElement) -> u128 {
+pub use crate::der::reader::{Reader, SliceReader};
+
+/// Convert the element to `u128`, returns `None` if it's out of range.
+pub(crate) fn u128_from_field_element(field_element: FieldElement) -> Option<u128> {
     let data = field_element.to_bytes_be();
+    if data[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
     let mut bytes = [0u8; 16];
     bytes.copy_from_slice(&data[16..]);
-    u128::from_be_bytes(bytes)
+    Some(u128::from_be_bytes(bytes))
 }
 
 pub trait Decode: Sized {
@@ -23,6 +32,14 @@ pub trait Decoder {
     // /// Claim that `n` field elements are going to be read from the decoder.
     // /// This can be used to validate `Configuration::Limit<N>()`.
     fn claim_field_elements_read(&mut self, n: usize) -> Result<(), DecodeError>;
+
+    /// The number of field elements that have been read, which is the offset of the next element.
+    fn field_elements_read(&self) -> usize;
+
+    /// The options of the decoding
+    fn config(&self) -> Config {
+        Config::default()
+    }
 }
 
 impl<'a, T> Decoder for &'a mut T
@@ -39,20 +56,37 @@ where
     fn claim_field_elements_read(&mut self, n: usize) -> Result<(), DecodeError> {
         T::claim_field_elements_read(self, n)
     }
+
+    #[inline]
+    fn field_elements_read(&self) -> usize {
+        T::field_elements_read(self)
+    }
+
+    #[inline]
+    fn config(&self) -> Config {
+        T::config(self)
+    }
 }
 
 #[derive(Debug)]
 pub struct DecoderImpl<R> {
     reader: R,
     field_elements_read: usize,
+    config: Config,
 }
 
 impl<R: Reader> DecoderImpl<R> {
     /// Construct a new Decoder
     pub fn new(reader: R) -> DecoderImpl<R> {
+        Self::with_config(reader, Config::default())
+    }
+
+    /// Construct a new Decoder with the options of `config`
+    pub fn with_config(reader: R, config: Config) -> DecoderImpl<R> {
         DecoderImpl {
             reader,
             field_elements_read: 0,
+            config,
         }
     }
 }
@@ -73,6 +107,15 @@ where
         Ok(())
     }
 
+    #[inline]
+    fn field_elements_read(&self) -> usize {
+        self.field_elements_read
+    }
+
+    fn config(&self) -> Config {
+        self.config
+    }
+
     // #[inline]
     // fn unclaim_field_elements_read(&mut self, n: usize) {
     //     // C::LIMIT is a const so this check should get compiled away
@@ -98,28 +141,88 @@ impl Decode for FieldElement {
 
 impl Decode for bool {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        match u8::decode(decoder)? {
-            0 => Ok(false),
-            1 => Ok(true),
-            _ => Err(DecodeError::OutOfRange),
+        match decoder.config().bool_mode {
+            BoolMode::Strict => {
+                decode_unsigned(decoder, "bool", |element| match u8::try_from(element) {
+                    Ok(0) => Some(false),
+                    Ok(1) => Some(true),
+                    _ => None,
+                })
+            }
+            BoolMode::NonZero => FieldElement::decode(decoder).map(|e| e != FieldElement::ZERO),
         }
     }
 }
 
+/// Decode the next element with `convert`, the element is only consumed if it's converted,
+/// otherwise the error is built from the element and its offset.
+#[inline]
+fn decode_element_with<D, T, F, E>(decoder: &mut D, convert: F, error: E) -> Result<T, DecodeError>
+where
+    D: Decoder,
+    F: FnOnce(FieldElement) -> Option<T>,
+    E: FnOnce(FieldElement, usize) -> DecodeError,
+{
+    let element = match decoder.reader().peek_read(1) {
+        Some(buf) => buf[0],
+        None => return Err(DecodeError::UnexpectedEnd { additional: 1 }),
+    };
+    let value = convert(element).ok_or_else(|| error(element, decoder.field_elements_read()))?;
+    decoder.claim_field_elements_read(1)?;
+    decoder.reader().consume(1);
+    Ok(value)
+}
+
+/// Decode an unsigned integer from the next element, the element is only consumed if it's in
+/// the range of the integer type.
+#[inline]
+fn decode_unsigned<D, T, F>(
+    decoder: &mut D,
+    type_name: &'static str,
+    convert: F,
+) -> Result<T, DecodeError>
+where
+    D: Decoder,
+    F: FnOnce(FieldElement) -> Option<T>,
+{
+    decode_element_with(decoder, convert, |element, offset| {
+        DecodeError::IntegerOutOfRange {
+            type_name,
+            element,
+            offset,
+        }
+    })
+}
+
+/// Decode the variant index of the enum `type_name`, which is the index in `variants` like the
+/// `Serde` derive of cairo 1.
+pub(crate) fn decode_variant_index<D: Decoder>(
+    decoder: &mut D,
+    type_name: &'static str,
+    variants: &'static [&'static str],
+) -> Result<u32, DecodeError> {
+    decode_element_with(
+        decoder,
+        |element| {
+            u32::try_from(element)
+                .ok()
+                .filter(|i| (*i as usize) < variants.len())
+        },
+        |element, offset| DecodeError::InvalidVariant {
+            type_name,
+            variants,
+            element,
+            offset,
+        },
+    )
+}
+
 macro_rules! impl_decode_for_unsigned_num {
     ($ty: ty) => {
         impl Decode for $ty {
             #[inline]
             fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-                decoder.claim_field_elements_read(1)?;
-                if let Some(buf) = decoder.reader().peek_read(1) {
-                    let element = buf[0];
-                    decoder.reader().consume(1);
-                    let num: $ty = element.try_into().map_err(|_e| DecodeError::OutOfRange)?;
-                    Ok(num)
-                } else {
-                    Err(DecodeError::UnexpectedEnd { additional: 1 })
-                }
+                decode_unsigned(decoder, stringify!($ty), |element| element.try_into().ok())
             }
         }
     };
@@ -143,29 +246,17 @@ impl_decode_for_unsigned_num!(u64);
 
 impl Decode for u128 {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        decoder.claim_field_elements_read(1)?;
-        if let Some(elements) = decoder.reader().peek_read(1) {
-            let element = elements[0];
-            decoder.reader().consume(1);
-            let num = u128_from_field_element(element);
-            Ok(num)
-        } else {
-            Err(DecodeError::OutOfRange)
-        }
+        decode_unsigned(decoder, "u128", u128_from_field_element)
     }
 }
 
 impl Decode for usize {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        decoder.claim_field_elements_read(1)?;
-        if let Some(elements) = decoder.reader().peek_read(1) {
-            let element = elements[0];
-            decoder.reader().consume(1);
-            let num = u64::try_from(element).unwrap();
-            Ok(num as usize)
-        } else {
-            Err(DecodeError::OutOfRange)
-        }
+        decode_unsigned(decoder, "usize", |element| {
+            u64::try_from(element)
+                .ok()
+                .and_then(|num| usize::try_from(num).ok())
+        })
     }
 }
 
@@ -177,7 +268,15 @@ impl_decode_with_error!(i64, "i64");
 impl_decode_with_error!(i128, "i128");
 impl_decode_with_error!(f32, "f32");
 impl_decode_with_error!(f64, "f64");
-impl_decode_with_error!(char, "char");
+
+/// A unicode scalar value
+impl Decode for char {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        decode_unsigned(decoder, "char", |element| {
+            u32::try_from(element).ok().and_then(char::from_u32)
+        })
+    }
+}
 
 impl Decode for String {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
@@ -189,7 +288,7 @@ impl Decode for String {
             let s = s.trim_start_matches('0');
             Ok(s.into())
         } else {
-            Err(DecodeError::OutOfRange)
+            Err(DecodeError::UnexpectedEnd { additional: 1 })
         }
     }
 }
@@ -198,8 +297,14 @@ impl<T, const N: usize> Decode for [T; N]
 where
     T: Decode + Sized + 'static,
 {
-    fn decode<D: Decoder>(_decoder: &mut D) -> Result<Self, DecodeError> {
-        Err(DecodeError::NotSupport("Slice".into()))
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let items = (0..N)
+            .map(|_| T::decode(decoder))
+            .collect::<Result<Vec<_>, _>>()?;
+        let found = items.len();
+        items
+            .try_into()
+            .map_err(|_| DecodeError::ArrayLengthMismatch { required: N, found })
     }
 }
 
@@ -219,14 +324,10 @@ impl<T> Decode for core::marker::PhantomData<T> {
 #[inline]
 pub fn decode_option_variant<D: Decoder>(
     decoder: &mut D,
-    _type_name: &'static str,
+    type_name: &'static str,
 ) -> Result<Option<()>, DecodeError> {
-    let is_some = u8::decode(decoder)?;
-    match is_some {
-        0 => Ok(None),
-        1 => Ok(Some(())),
-        _x => Err(DecodeError::OutOfRange),
-    }
+    let index = decode_variant_index(decoder, type_name, &["Some", "None"])?;
+    Ok((index == OPTION_SOME).then_some(()))
 }
 
 impl<T> Decode for Option<T>
@@ -250,17 +351,11 @@ where
     U: Decode,
 {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let is_ok = u32::decode(decoder)?;
-        match is_ok {
-            0 => {
-                let t = T::decode(decoder)?;
-                Ok(Ok(t))
-            }
-            1 => {
-                let u = U::decode(decoder)?;
-                Ok(Err(u))
-            }
-            _ => Err(DecodeError::OutOfRange),
+        let index = decode_variant_index(decoder, "Result", &["Ok", "Err"])?;
+        if index == RESULT_OK {
+            Ok(Ok(T::decode(decoder)?))
+        } else {
+            Ok(Err(U::decode(decoder)?))
         }
     }
 }
@@ -280,9 +375,74 @@ where
     }
 }
 
+impl<K, V, S> Decode for HashMap<K, V, S>
+where
+    K: Decode + Eq + Hash,
+    V: Decode,
+    S: BuildHasher + Default,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let len = usize::decode(decoder)?;
+        let mut result = HashMap::with_hasher(S::default());
+        for _ in 0..len {
+            let k = K::decode(decoder)?;
+            let v = V::decode(decoder)?;
+            result.insert(k, v);
+        }
+        Ok(result)
+    }
+}
+
+impl<K, V> Decode for BTreeMap<K, V>
+where
+    K: Decode + Ord,
+    V: Decode,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let len = usize::decode(decoder)?;
+        let mut result = BTreeMap::new();
+        for _ in 0..len {
+            let k = K::decode(decoder)?;
+            let v = V::decode(decoder)?;
+            result.insert(k, v);
+        }
+        Ok(result)
+    }
+}
+
+impl<T, S> Decode for HashSet<T, S>
+where
+    T: Decode + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let len = usize::decode(decoder)?;
+        let mut result = HashSet::with_hasher(S::default());
+        for _ in 0..len {
+            result.insert(T::decode(decoder)?);
+        }
+        Ok(result)
+    }
+}
+
+impl<T> Decode for BTreeSet<T>
+where
+    T: Decode + Ord,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let len = usize::decode(decoder)?;
+        let mut result = BTreeSet::new();
+        for _ in 0..len {
+            result.insert(T::decode(decoder)?);
+        }
+        Ok(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::from_slice;
     use starknet::core::types::FieldElement;
 
     #[test]
@@ -312,6 +472,180 @@ mod tests {
         let f = FieldElement::from_byte_slice_be(&data.to_vec());
         assert!(f.is_ok());
         let num = u128_from_field_element(f.unwrap());
-        assert_eq!(is_u128, num);
+        assert_eq!(Some(is_u128), num);
+
+        let f = FieldElement::from(u128::MAX) + FieldElement::ONE;
+        assert_eq!(u128_from_field_element(f), None);
+    }
+
+    #[test]
+    fn test_decode_unsigned_out_of_range() {
+        let big = FieldElement::from(u128::MAX) + FieldElement::ONE;
+        let elements = vec![FieldElement::ONE, big];
+
+        let err = from_slice::<(u8, u128)>(&elements).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::IntegerOutOfRange {
+                type_name: "u128",
+                element,
+                offset: 1,
+            } if *element == big
+        ));
+        // the length of a sequence
+        let err = from_slice::<(u8, Vec<u8>)>(&elements).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::IntegerOutOfRange {
+                type_name: "usize",
+                offset: 1,
+                ..
+            }
+        ));
+        let err = from_slice::<(u8, u64)>(&elements).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::IntegerOutOfRange {
+                type_name: "u64",
+                offset: 1,
+                ..
+            }
+        ));
+
+        for err in [
+            from_slice::<(u8, u128)>(&elements[..1]).unwrap_err(),
+            from_slice::<(u8, Vec<u8>)>(&elements[..1]).unwrap_err(),
+            from_slice::<(u8, String)>(&elements[..1]).unwrap_err(),
+        ] {
+            assert!(matches!(
+                err.root_cause(),
+                DecodeError::UnexpectedEnd { additional: 1 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_huge_len() {
+        use std::collections::{HashMap, HashSet};
+
+        // a length from the input doesn't allocate before the entries are read
+        let elements = [FieldElement::from(1u64 << 60), FieldElement::ONE];
+        let mut decoder = DecoderImpl::new(SliceReader::new(&elements));
+        assert!(HashMap::<u8, u8>::decode(&mut decoder).is_err());
+        let mut decoder = DecoderImpl::new(SliceReader::new(&elements));
+        assert!(HashSet::<u8>::decode(&mut decoder).is_err());
+    }
+
+    #[test]
+    fn test_cairo_enum_layout() {
+        use crate::encoder::{Encode, EncoderImpl};
+        use crate::to_field_elements;
+        use serde::{Deserialize, Serialize};
+
+        let felts = |v: &[u8]| v.iter().map(|x| FieldElement::from(*x)).collect::<Vec<_>>();
+        let native = |value: &dyn Fn(&mut EncoderImpl)| {
+            let mut encoder = EncoderImpl::new(Default::default());
+            value(&mut encoder);
+            encoder.filed_elements
+        };
+
+        assert_eq!(to_field_elements(Some(5u8)).unwrap(), felts(&[0, 5]));
+        assert_eq!(to_field_elements(None::<u8>).unwrap(), felts(&[1]));
+        assert_eq!(native(&|e| Some(5u8).encode(e).unwrap()), felts(&[0, 5]));
+        assert_eq!(from_slice::<Option<u8>>(&felts(&[0, 5])).unwrap(), Some(5));
+        assert_eq!(from_slice::<Option<u8>>(&felts(&[1])).unwrap(), None);
+
+        let ok: Result<u8, u16> = Ok(1);
+        let err: Result<u8, u16> = Err(2);
+        assert_eq!(to_field_elements(ok).unwrap(), felts(&[0, 1]));
+        assert_eq!(to_field_elements(err).unwrap(), felts(&[1, 2]));
+        assert_eq!(native(&|e| err.encode(e).unwrap()), felts(&[1, 2]));
+        assert_eq!(from_slice::<Result<u8, u16>>(&felts(&[1, 2])).unwrap(), err);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Op {
+            Noop,
+            Deposit(u8),
+            Transfer { from: u8, to: u8 },
+        }
+        let op = Op::Transfer { from: 1, to: 2 };
+        assert_eq!(to_field_elements(&op).unwrap(), felts(&[2, 1, 2]));
+        assert_eq!(from_slice::<Op>(&felts(&[2, 1, 2])).unwrap(), op);
+        assert_eq!(from_slice::<Op>(&felts(&[1, 7])).unwrap(), Op::Deposit(7));
+        assert_eq!(from_slice::<Op>(&felts(&[0])).unwrap(), Op::Noop);
+
+        let err = from_slice::<(u8, Op)>(&felts(&[0, 3])).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::InvalidVariant {
+                type_name: "Op",
+                variants: ["Noop", "Deposit", "Transfer"],
+                offset: 1,
+                ..
+            }
+        ));
+        let err = from_slice::<Option<u8>>(&felts(&[2])).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::InvalidVariant {
+                variants: ["Some", "None"],
+                ..
+            }
+        ));
+
+        // identifiers are decoded from their index
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(field_identifier)]
+        enum Field {
+            Low,
+            High,
+        }
+        assert_eq!(from_slice::<Field>(&felts(&[1])).unwrap(), Field::High);
+        let err = from_slice::<Field>(&felts(&[2])).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::InvalidIdentifier {
+                index: 2,
+                offset: 0
+            }
+        ));
+
+        #[derive(Debug, Deserialize)]
+        #[serde(tag = "type")]
+        enum Tagged {
+            A { a: u8 },
+        }
+        let err = from_slice::<Tagged>(&felts(&[0, 1])).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::NotSupport(s) if s == "internally tagged enum Tagged"
+        ));
+    }
+
+    #[test]
+    fn test_char_and_bool() {
+        use crate::config::{BoolMode, Config};
+        use crate::{from_slice_with_config, to_field_elements};
+
+        let encoded = to_field_elements(('é', '🦀')).unwrap();
+        assert_eq!(
+            encoded,
+            vec![FieldElement::from(0xe9u32), FieldElement::from(0x1f980u32)]
+        );
+        assert_eq!(from_slice::<(char, char)>(&encoded).unwrap(), ('é', '🦀'));
+        let surrogate = [FieldElement::from(0xd800u32)];
+        assert!(matches!(
+            from_slice::<char>(&surrogate).unwrap_err().root_cause(),
+            DecodeError::IntegerOutOfRange {
+                type_name: "char",
+                ..
+            }
+        ));
+
+        let two = [FieldElement::TWO];
+        assert!(from_slice::<bool>(&two).is_err());
+        let non_zero = Config::new().with_bool_mode(BoolMode::NonZero);
+        assert!(from_slice_with_config::<bool>(&two, non_zero).unwrap());
+        assert!(!from_slice_with_config::<bool>(&[FieldElement::ZERO], non_zero).unwrap());
     }
 }
@@ -1,22 +1,161 @@
-use crate::decoder::{decode_option_variant, Decode, Decoder};
-use crate::error::DecodeError;
+use crate::array::FELTS_NAME;
+use crate::decoder::{decode_option_variant, decode_variant_index, Decode, Decoder};
+use crate::der::reader::Reader;
+use crate::error::{DecodeError, PathSegment};
+use crate::packed::BytesMode;
+use crate::serde::{decode_native, with_native_config, NATIVE_NAME};
+use crate::versioned::VERSIONED_NAME;
 use serde::de::{
-    DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+    DeserializeSeed, EnumAccess, Expected, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
 };
 use serde::Deserializer;
 
 pub(crate) struct SerdeDecoder<'a, DE: Decoder> {
     pub(crate) de: &'a mut DE,
+    /// The encoding of `deserialize_byte_buf`
+    bytes: BytesMode,
+    /// Whether the fields at the end of the struct may be missing when the input ends, they are
+    /// then filled by `#[serde(default)]`. The fields of the struct don't inherit it.
+    missing_fields: bool,
+}
+
+impl<'a, DE: Decoder> SerdeDecoder<'a, DE> {
+    pub(crate) fn new(de: &'a mut DE) -> Self {
+        Self {
+            de,
+            bytes: BytesMode::default(),
+            missing_fields: false,
+        }
+    }
+
+    /// Allow the fields at the end of the struct to be missing, see [crate::versioned::Versioned].
+    pub(crate) fn allowing_missing_fields(de: &'a mut DE) -> Self {
+        Self {
+            missing_fields: true,
+            ..Self::new(de)
+        }
+    }
+
+    /// Deserialize the next field, errors are annotated with the field path and the number of
+    /// elements read so far.
+    fn deserialize_field<'de, T>(
+        &mut self,
+        seed: T,
+        segment: PathSegment,
+    ) -> Result<T::Value, DecodeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(SerdeDecoder::new(self.de))
+            .map_err(|e| e.in_field(segment, self.de.field_elements_read()))
+    }
+
+    /// Deserialize `len` consecutive elements, named by `fields` for structs and indexed for
+    /// sequences and tuples.
+    fn deserialize_elements<'de, V>(
+        mut self,
+        len: usize,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError>
+    where
+        V: Visitor<'de>,
+    {
+        struct Access<'a, 'b, DE: Decoder> {
+            deserializer: &'a mut SerdeDecoder<'b, DE>,
+            len: usize,
+            index: usize,
+            fields: Option<&'static [&'static str]>,
+        }
+
+        impl<'de, 'a, 'b: 'a, DE: Decoder + 'b> SeqAccess<'de> for Access<'a, 'b, DE> {
+            type Error = DecodeError;
+
+            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DecodeError>
+            where
+                T: DeserializeSeed<'de>,
+            {
+                if self.fields.is_some()
+                    && self.deserializer.missing_fields
+                    && self.deserializer.de.reader().peek_read(1).is_none()
+                {
+                    // the remaining fields are missing
+                    self.len = 0;
+                }
+                if self.len > 0 {
+                    self.len -= 1;
+                    let segment = match self.fields.and_then(|f| f.get(self.index)) {
+                        Some(name) => PathSegment::Field(name),
+                        None => PathSegment::Index(self.index),
+                    };
+                    self.index += 1;
+                    let value = self.deserializer.deserialize_field(seed, segment)?;
+                    Ok(Some(value))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.len)
+            }
+        }
+
+        visitor.visit_seq(Access {
+            deserializer: &mut self,
+            len,
+            index: 0,
+            fields,
+        })
+    }
+}
+
+impl<'a, DE: Decoder> SerdeDecoder<'a, DE> {
+    /// Deserialize the remaining elements as a sequence, see [crate::array::Felts].
+    fn deserialize_remaining<'de, V>(mut self, visitor: V) -> Result<V::Value, DecodeError>
+    where
+        V: Visitor<'de>,
+    {
+        struct Access<'a, 'b, DE: Decoder> {
+            deserializer: &'a mut SerdeDecoder<'b, DE>,
+            index: usize,
+        }
+
+        impl<'de, 'a, 'b: 'a, DE: Decoder + 'b> SeqAccess<'de> for Access<'a, 'b, DE> {
+            type Error = DecodeError;
+
+            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DecodeError>
+            where
+                T: DeserializeSeed<'de>,
+            {
+                if self.deserializer.de.reader().peek_read(1).is_none() {
+                    return Ok(None);
+                }
+                let segment = PathSegment::Index(self.index);
+                self.index += 1;
+                self.deserializer.deserialize_field(seed, segment).map(Some)
+            }
+        }
+
+        visitor.visit_seq(Access {
+            deserializer: &mut self,
+            index: 0,
+        })
+    }
 }
 
 impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
     type Error = DecodeError;
 
-    fn deserialize_any<V>(self, _: V) -> Result<V::Value, Self::Error>
+    /// The encoding isn't self-describing, e.g. internally tagged and untagged enums need this.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        Err(DecodeError::AnyNotSupported)
+        Err(DecodeError::NotSupport(
+            (&visitor as &dyn Expected).to_string(),
+        ))
     }
 
     fn deserialize_bool<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
@@ -121,11 +260,11 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
         visitor.visit_char(Decode::decode(&mut self.de)?)
     }
 
-    fn deserialize_str<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_string(Decode::decode(&mut self.de)?)
+        self.deserialize_string(visitor)
     }
 
     fn deserialize_string<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
@@ -146,7 +285,7 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_byte_buf(Decode::decode(&mut self.de)?)
+        visitor.visit_byte_buf(self.bytes.decode(&mut self.de)?)
     }
 
     fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
@@ -181,13 +320,28 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        if name == FELTS_NAME {
+            return self.deserialize_remaining(visitor);
+        }
+        if name == NATIVE_NAME {
+            // the value pulls the elements it decodes, with the config of this decoder
+            let (config, offset) = (self.de.config(), self.de.field_elements_read());
+            return decode_native(config, offset, || self.deserialize_remaining(visitor));
+        }
+        if name == VERSIONED_NAME {
+            let config = self.de.config();
+            return with_native_config(config, || visitor.visit_newtype_struct(self));
+        }
+        match BytesMode::from_newtype_name(name) {
+            Some(bytes) => visitor.visit_newtype_struct(SerdeDecoder { bytes, ..self }),
+            None => visitor.visit_newtype_struct(self),
+        }
     }
 
     fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
@@ -198,45 +352,11 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
         self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        struct Access<'a, 'b, DE: Decoder> {
-            deserializer: &'a mut SerdeDecoder<'b, DE>,
-            len: usize,
-        }
-
-        impl<'de, 'a, 'b: 'a, DE: Decoder + 'b> SeqAccess<'de> for Access<'a, 'b, DE> {
-            type Error = DecodeError;
-
-            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DecodeError>
-            where
-                T: DeserializeSeed<'de>,
-            {
-                if self.len > 0 {
-                    self.len -= 1;
-                    let value = DeserializeSeed::deserialize(
-                        seed,
-                        SerdeDecoder {
-                            de: self.deserializer.de,
-                        },
-                    )?;
-                    Ok(Some(value))
-                } else {
-                    Ok(None)
-                }
-            }
-
-            fn size_hint(&self) -> Option<usize> {
-                Some(self.len)
-            }
-        }
-
-        visitor.visit_seq(Access {
-            deserializer: &mut self,
-            len,
-        })
+        self.deserialize_elements(len, None, visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -258,6 +378,7 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
         struct Access<'a, 'b, DE: Decoder> {
             deserializer: &'a mut SerdeDecoder<'b, DE>,
             len: usize,
+            index: usize,
         }
 
         impl<'de, 'a, 'b: 'a, DE: Decoder + 'b> MapAccess<'de> for Access<'a, 'b, DE> {
@@ -269,12 +390,9 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
             {
                 if self.len > 0 {
                     self.len -= 1;
-                    let key = DeserializeSeed::deserialize(
-                        seed,
-                        SerdeDecoder {
-                            de: self.deserializer.de,
-                        },
-                    )?;
+                    let key = self
+                        .deserializer
+                        .deserialize_field(seed, PathSegment::Index(self.index))?;
                     Ok(Some(key))
                 } else {
                     Ok(None)
@@ -285,12 +403,10 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
             where
                 V: DeserializeSeed<'de>,
             {
-                let value = DeserializeSeed::deserialize(
-                    seed,
-                    SerdeDecoder {
-                        de: self.deserializer.de,
-                    },
-                )?;
+                let value = self
+                    .deserializer
+                    .deserialize_field(seed, PathSegment::Index(self.index))?;
+                self.index += 1;
                 Ok(value)
             }
 
@@ -304,6 +420,7 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
         visitor.visit_map(Access {
             deserializer: &mut self,
             len,
+            index: 0,
         })
     }
 
@@ -316,26 +433,33 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_tuple(fields.len(), visitor)
+        self.deserialize_elements(fields.len(), Some(fields), visitor)
     }
 
     fn deserialize_enum<V>(
-        self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(self)
+        let index = decode_variant_index(&mut self.de, name, variants)?;
+        visitor.visit_enum(Enum { index, de: self })
     }
 
-    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    /// Identifiers are encoded as their index, like the variants of enums, which is checked by the
+    /// visitor as it knows the identifiers.
+    fn deserialize_identifier<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        Err(DecodeError::IdentifierNotSupported)
+        let offset = self.de.field_elements_read();
+        let index = u32::decode(&mut self.de)?;
+        visitor
+            .visit_u32::<DecodeError>(index)
+            .map_err(|_| DecodeError::InvalidIdentifier { index, offset })
     }
 
     fn deserialize_ignored_any<V>(self, _: V) -> Result<V::Value, Self::Error>
@@ -350,17 +474,22 @@ impl<'a, 'de, DE: Decoder> Deserializer<'de> for SerdeDecoder<'a, DE> {
     }
 }
 
-impl<'de, 'a, DE: Decoder> EnumAccess<'de> for SerdeDecoder<'a, DE> {
+/// An enum whose variant index has been decoded
+struct Enum<'a, DE: Decoder> {
+    index: u32,
+    de: SerdeDecoder<'a, DE>,
+}
+
+impl<'de, 'a, DE: Decoder> EnumAccess<'de> for Enum<'a, DE> {
     type Error = DecodeError;
-    type Variant = Self;
+    type Variant = SerdeDecoder<'a, DE>;
 
-    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
     where
         V: DeserializeSeed<'de>,
     {
-        let idx = u32::decode(&mut self.de)?;
-        let val = seed.deserialize(idx.into_deserializer())?;
-        Ok((val, self))
+        let val = seed.deserialize(self.index.into_deserializer())?;
+        Ok((val, self.de))
     }
 }
 
@@ -393,6 +522,6 @@ impl<'de, 'a, DE: Decoder> VariantAccess<'de> for SerdeDecoder<'a, DE> {
     where
         V: Visitor<'de>,
     {
-        Deserializer::deserialize_tuple(self, fields.len(), visitor)
+        self.deserialize_elements(fields.len(), Some(fields), visitor)
     }
 }
@@ -1,8 +1,22 @@
-use crate::error::EncodeError;
+use crate::config::{Config, MapOrder};
+use crate::error::{EncodeError, PathSegment};
 use starknet::core::types::FieldElement;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 pub struct EncoderImpl {
     pub filed_elements: Vec<FieldElement>,
+    config: Config,
+}
+
+impl EncoderImpl {
+    /// Construct a new Encoder with the options of `config`
+    pub fn new(config: Config) -> Self {
+        Self {
+            filed_elements: vec![],
+            config,
+        }
+    }
 }
 
 pub trait Encode {
@@ -13,6 +27,32 @@ pub trait Encode {
 
 pub trait Encoder {
     fn push_field_element(&mut self, element: FieldElement);
+
+    /// The options of the encoding
+    fn config(&self) -> Config {
+        Config::default()
+    }
+
+    // The hooks of [crate::explain_field_elements], they aren't part of the public api.
+
+    /// Called before the elements of the field or element `segment` are pushed, so an encoder
+    /// can track the path of the elements.
+    #[doc(hidden)]
+    fn enter(&mut self, _segment: PathSegment) {}
+
+    /// Called after the elements of the last entered segment are pushed.
+    #[doc(hidden)]
+    fn leave(&mut self) {}
+
+    /// Whether [Encoder::describe] is used, the descriptions aren't built otherwise.
+    #[doc(hidden)]
+    fn wants_descriptions(&self) -> bool {
+        false
+    }
+
+    /// Called with the rust type of the elements pushed next.
+    #[doc(hidden)]
+    fn describe(&mut self, _type_name: &str) {}
 }
 
 impl Encoder for EncoderImpl {
@@ -20,22 +60,179 @@ impl Encoder for EncoderImpl {
     fn push_field_element(&mut self, element: FieldElement) {
         self.filed_elements.push(element);
     }
+
+    fn config(&self) -> Config {
+        self.config
+    }
 }
 
+/// The variant index of `Option::Some`, cairo 1 declares `Option` as `enum { Some: T, None: () }`
+pub(crate) const OPTION_SOME: u32 = 0;
+/// The variant index of `Option::None`
+pub(crate) const OPTION_NONE: u32 = 1;
+/// The variant index of `Result::Ok`, cairo 1 declares `Result` as `enum { Ok: T, Err: E }`
+pub(crate) const RESULT_OK: u32 = 0;
+/// The variant index of `Result::Err`
+const RESULT_ERR: u32 = 1;
+
 #[inline]
 pub(crate) fn encode_option_variant<E: Encoder, T>(
     encoder: &mut E,
     value: &Option<T>,
 ) -> Result<(), EncodeError> {
     match value {
-        None => 0u8.encode(encoder),
-        Some(_) => 1u8.encode(encoder),
+        None => OPTION_NONE.encode(encoder),
+        Some(_) => OPTION_SOME.encode(encoder),
     }
 }
 
 #[inline]
 pub(crate) fn encode_slice_len<E: Encoder>(encoder: &mut E, len: usize) -> Result<(), EncodeError> {
-    len.encode(encoder)
+    encoder.enter(PathSegment::Field("len"));
+    encoder.describe("usize");
+    let result = len.encode(encoder);
+    encoder.leave();
+    result
+}
+
+/// The encoded key and value of a map entry, the value of a set entry is empty
+pub(crate) type EncodedEntry = (Vec<FieldElement>, Vec<FieldElement>);
+
+/// Encode `value` on its own, with the config of `encoder`.
+pub(crate) fn encode_to_vec<E: Encoder, T: Encode + ?Sized>(
+    encoder: &E,
+    value: &T,
+) -> Result<Vec<FieldElement>, EncodeError> {
+    let mut buf = EncoderImpl::new(encoder.config());
+    value.encode(&mut buf)?;
+    Ok(buf.filed_elements)
+}
+
+/// Compare the encodings by the value of their elements.
+fn cmp_encoded(a: &[FieldElement], b: &[FieldElement]) -> Ordering {
+    a.iter()
+        .map(FieldElement::to_bytes_be)
+        .cmp(b.iter().map(FieldElement::to_bytes_be))
+}
+
+/// Encode the length and the entries of a map or set, ordered by the encoding of their keys and
+/// checked as required by [Config::map_order].
+///
+/// `types` are the rust types of the keys and values, which describe the entries to the encoder.
+pub(crate) fn encode_map_entries<E: Encoder>(
+    encoder: &mut E,
+    mut entries: Vec<EncodedEntry>,
+    types: (&str, &str),
+) -> Result<(), EncodeError> {
+    entries.sort_by(|a, b| cmp_encoded(&a.0, &b.0));
+    // different keys with the same encoding can't be decoded back
+    if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(EncodeError::DuplicateMapKey);
+    }
+    if encoder.config().map_order == MapOrder::Strict
+        && entries.windows(2).any(|w| w[0].0.len() != w[1].0.len())
+    {
+        return Err(EncodeError::MixedKeyLengths);
+    }
+    encode_slice_len(encoder, entries.len())?;
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        encoder.enter(PathSegment::Index(i));
+        for (name, elements, type_name) in [("key", key, types.0), ("value", value, types.1)] {
+            encoder.enter(PathSegment::Field(name));
+            encoder.describe(type_name);
+            for element in elements {
+                encoder.push_field_element(element);
+            }
+            encoder.leave();
+        }
+        encoder.leave();
+    }
+    Ok(())
+}
+
+fn encode_map<'a, E, K, V, I>(encoder: &mut E, iter: I) -> Result<(), EncodeError>
+where
+    E: Encoder,
+    K: Encode + 'a,
+    V: Encode + 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    let entries = iter
+        .map(|(k, v)| Ok((encode_to_vec(encoder, k)?, encode_to_vec(encoder, v)?)))
+        .collect::<Result<_, EncodeError>>()?;
+    let types = (
+        describe_type::<_, K>(encoder),
+        describe_type::<_, V>(encoder),
+    );
+    encode_map_entries(encoder, entries, (&types.0, &types.1))
+}
+
+fn encode_set<'a, E, T, I>(encoder: &mut E, iter: I) -> Result<(), EncodeError>
+where
+    E: Encoder,
+    T: Encode + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    let entries = iter
+        .map(|t| Ok((encode_to_vec(encoder, t)?, vec![])))
+        .collect::<Result<_, EncodeError>>()?;
+    let type_name = describe_type::<_, T>(encoder);
+    encode_map_entries(encoder, entries, (&type_name, "()"))
+}
+
+/// The [short_type_name] of `T` if `encoder` wants descriptions, an empty string otherwise.
+pub(crate) fn describe_type<E: Encoder, T: ?Sized>(encoder: &E) -> String {
+    if encoder.wants_descriptions() {
+        short_type_name::<T>()
+    } else {
+        String::new()
+    }
+}
+
+/// The name of `T` without module paths and references, e.g. `Vec<U256>`.
+pub(crate) fn short_type_name<T: ?Sized>() -> String {
+    strip_module_paths(std::any::type_name::<T>().trim_start_matches('&'))
+}
+
+/// Remove the modules of the paths in a type name, e.g. `core::array::Array<core::felt252>`
+/// becomes `Array<felt252>`.
+pub(crate) fn strip_module_paths(type_name: &str) -> String {
+    let mut name = String::new();
+    for c in type_name.chars() {
+        name.push(c);
+        if name.ends_with("::") {
+            // drop the module, which starts after the last delimiter
+            let start = name[..name.len() - 2]
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(0, |i| i + 1);
+            name.truncate(start);
+        }
+    }
+    name
+}
+
+impl<K: Encode, V: Encode, S> Encode for HashMap<K, V, S> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        encode_map(encoder, self.iter())
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        encode_map(encoder, self.iter())
+    }
+}
+
+impl<T: Encode, S> Encode for HashSet<T, S> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        encode_set(encoder, self.iter())
+    }
+}
+
+impl<T: Encode> Encode for BTreeSet<T> {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        encode_set(encoder, self.iter())
+    }
 }
 
 impl Encode for () {
@@ -84,7 +281,13 @@ impl Encode for i64 {}
 impl Encode for i128 {}
 impl Encode for f32 {}
 impl Encode for f64 {}
-impl Encode for char {}
+
+/// A unicode scalar value
+impl Encode for char {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        u32::from(*self).encode(encoder)
+    }
+}
 
 impl<T> Encode for [T]
 where
@@ -99,11 +302,20 @@ where
     }
 }
 
+/// The max number of chars of an invalid string kept in [EncodeError::InvalidString]
+const INVALID_STRING_MAX_CHARS: usize = 80;
+
 /// only encode hash, which starts with "0x" or not
 impl Encode for str {
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         let s = self.strip_prefix("0x").unwrap_or(self);
-        let field = FieldElement::from_hex_be(s).map_err(|_| EncodeError::InvalidString)?;
+        let field = FieldElement::from_hex_be(s).map_err(|_| {
+            let mut value: String = self.chars().take(INVALID_STRING_MAX_CHARS).collect();
+            if value.len() < self.len() {
+                value.push_str("...");
+            }
+            EncodeError::InvalidString(value)
+        })?;
         field.encode(encoder)
     }
 }
@@ -148,11 +360,11 @@ where
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         match self {
             Ok(val) => {
-                0u32.encode(encoder)?;
+                RESULT_OK.encode(encoder)?;
                 val.encode(encoder)
             }
             Err(err) => {
-                1u32.encode(encoder)?;
+                RESULT_ERR.encode(encoder)?;
                 err.encode(encoder)
             }
         }
@@ -1,4 +1,5 @@
 use serde::ser;
+use starknet::core::types::FieldElement;
 use std::fmt::Display;
 use thiserror::Error;
 
@@ -12,8 +13,62 @@ pub enum EncodeError {
     UnSupport,
     #[error("{0}")]
     OtherString(String),
-    #[error("only support Hex string")]
-    InvalidString,
+    /// A string field must be a hex string of a field element, with or without the `0x` prefix
+    #[error("only support Hex string, found `{0}`")]
+    InvalidString(String),
+    /// Maps are encoded with their length, which `#[serde(flatten)]` doesn't provide
+    #[error("map must have length, `#[serde(flatten)]` is not supported")]
+    MapMustHaveLength,
+    /// Fields are decoded by position, so they can't be skipped by `#[serde(skip_serializing_if)]`
+    #[error("field `{0}` is skipped, `#[serde(skip_serializing_if)]` is not supported")]
+    SkippedField(&'static str),
+    /// The keys of a map or set have encodings of different lengths, see [crate::config::MapOrder::Strict]
+    #[error("map keys have encodings of different lengths")]
+    MixedKeyLengths,
+    /// [crate::array::Felts] takes the remaining elements when decoding, so no element may follow it
+    #[error("`Felts` must be the last field, elements follow it")]
+    FeltsNotLast,
+    /// Different keys of a map or set have the same encoding
+    #[error("duplicate map key encoding")]
+    DuplicateMapKey,
+    /// `source` occurred when encoding the field at `path`
+    #[error("{source} at `{path}`")]
+    Context {
+        path: FieldPath,
+        source: Box<EncodeError>,
+    },
+}
+
+impl EncodeError {
+    /// Record that the error occurred in the field `segment`.
+    pub(crate) fn in_field(self, segment: PathSegment) -> Self {
+        match self {
+            Self::Context { mut path, source } => {
+                path.0.insert(0, segment);
+                Self::Context { path, source }
+            }
+            source => Self::Context {
+                path: FieldPath(vec![segment]),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The error without its path
+    pub fn root_cause(&self) -> &EncodeError {
+        match self {
+            Self::Context { source, .. } => source.root_cause(),
+            e => e,
+        }
+    }
+
+    /// The path of the field where the error occurred
+    pub fn path(&self) -> Option<&FieldPath> {
+        match self {
+            Self::Context { path, .. } => Some(path),
+            _ => None,
+        }
+    }
 }
 
 impl ser::Error for EncodeError {
@@ -52,10 +107,187 @@ pub enum DecodeError {
     CannotBorrowOwnedData,
     #[error("Out of range")]
     OutOfRange,
+    /// The element at `offset` is out of the range of the integer type
+    #[error("element {element:#x} at offset {offset} is out of range of {type_name}")]
+    IntegerOutOfRange {
+        type_name: &'static str,
+        element: FieldElement,
+        offset: usize,
+    },
+    /// The element at `offset` is not the index of a variant of the enum `type_name`
+    #[error("element {element:#x} at offset {offset} is not a variant of {type_name}, expected the index of {variants:?}")]
+    InvalidVariant {
+        type_name: &'static str,
+        variants: &'static [&'static str],
+        element: FieldElement,
+        offset: usize,
+    },
+    /// The element at `offset` is not the index of an identifier of the visitor
+    #[error("element {index} at offset {offset} is not the index of an identifier")]
+    InvalidIdentifier { index: u32, offset: usize },
     #[error("Not support type {0}")]
     NotSupport(String),
     #[error("Invalid string")]
     InvalidString,
+    /// The words of packed bytes are inconsistent with their length
+    #[error("invalid packed bytes, {0}")]
+    InvalidPackedBytes(&'static str),
+    /// `source` occurred when decoding the field at `path`, after `offset` elements were read
+    #[error("{source}{}", context_suffix(*offset, path, source))]
+    Context {
+        offset: usize,
+        path: FieldPath,
+        source: Box<DecodeError>,
+    },
+}
+
+/// The offset is omitted when `source` already displays the offset of its element.
+fn context_suffix(offset: usize, path: &FieldPath, source: &DecodeError) -> String {
+    let has_offset = matches!(
+        source,
+        DecodeError::IntegerOutOfRange { .. }
+            | DecodeError::InvalidVariant { .. }
+            | DecodeError::InvalidIdentifier { .. }
+    );
+    match (has_offset, path.is_empty()) {
+        (true, true) => String::new(),
+        (true, false) => format!(" at `{path}`"),
+        (false, true) => format!(" (element {offset})"),
+        (false, false) => format!(" (element {offset}, at `{path}`)"),
+    }
+}
+
+impl DecodeError {
+    /// Record that the error occurred in the field `segment` after `offset` elements were read,
+    /// the offset is only recorded by the innermost field.
+    pub(crate) fn in_field(self, segment: PathSegment, offset: usize) -> Self {
+        match self {
+            Self::Context {
+                offset,
+                mut path,
+                source,
+            } => {
+                path.0.insert(0, segment);
+                Self::Context {
+                    offset,
+                    path,
+                    source,
+                }
+            }
+            source => Self::Context {
+                offset,
+                path: FieldPath(vec![segment]),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Record the offset of an error that occurred outside of any field.
+    pub(crate) fn at_offset(self, offset: usize) -> Self {
+        match self {
+            Self::Context { .. } => self,
+            source => Self::Context {
+                offset,
+                path: FieldPath::default(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Count the offsets of the error from `offset` elements earlier, for an error of a decoder
+    /// which started after those elements.
+    pub(crate) fn shift_offset(self, by: usize) -> Self {
+        match self {
+            Self::Context {
+                offset,
+                path,
+                source,
+            } => Self::Context {
+                offset: offset + by,
+                path,
+                source: Box::new(source.shift_offset(by)),
+            },
+            Self::IntegerOutOfRange {
+                type_name,
+                element,
+                offset,
+            } => Self::IntegerOutOfRange {
+                type_name,
+                element,
+                offset: offset + by,
+            },
+            Self::InvalidVariant {
+                type_name,
+                variants,
+                element,
+                offset,
+            } => Self::InvalidVariant {
+                type_name,
+                variants,
+                element,
+                offset: offset + by,
+            },
+            Self::InvalidIdentifier { index, offset } => Self::InvalidIdentifier {
+                index,
+                offset: offset + by,
+            },
+            error => error,
+        }
+    }
+
+    /// The error without its offset and path
+    pub fn root_cause(&self) -> &DecodeError {
+        match self {
+            Self::Context { source, .. } => source.root_cause(),
+            e => e,
+        }
+    }
+
+    /// The number of elements read before the error occurred
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Context { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The path of the field where the error occurred
+    pub fn path(&self) -> Option<&FieldPath> {
+        match self {
+            Self::Context { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// A struct field or a sequence/tuple index in a [FieldPath]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+/// The serde path of a field, e.g. `onchain_operations[2].eth_witness.data[5]`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldPath(pub Vec<PathSegment>);
+
+impl FieldPath {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if i == 0 => write!(f, "{name}")?,
+                PathSegment::Field(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
 }
 
 impl serde::de::Error for DecodeError {
@@ -66,3 +298,82 @@ impl serde::de::Error for DecodeError {
         Self::Other(msg.to_string())
     }
 }
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PubdataError {
+    /// The pubdata of an operation must have exactly `required` bytes
+    #[error("pubdata length mismatch, required {required}, but get {found}")]
+    LengthMismatch { required: usize, found: usize },
+    #[error("op type {found} mismatch, expect {expected}")]
+    OpTypeMismatch { expected: u8, found: u8 },
+    #[error("Not support op type {0}")]
+    UnsupportedOpType(u8),
+    /// A hex string field can't be packed into `len` bytes
+    #[error("invalid {len} bytes hex string {value}")]
+    InvalidHexString { value: String, len: usize },
+    /// The size of [crate::proto::Bytes] needs `(size + 15) / 16` words
+    #[error("{size} bytes mismatch {words} words")]
+    InvalidBytesSize { size: usize, words: usize },
+    /// The offset of an onchain operation must be the start of a chunk in the pubdata
+    #[error("invalid onchain operation offset {0}")]
+    InvalidOffset(usize),
+    #[error("pending word length {0} out of range")]
+    InvalidPendingWordLen(usize),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NumError {
+    #[error("invalid number string {0}")]
+    InvalidString(String),
+    #[error("number overflow")]
+    Overflow,
+    /// Only finite and non negative floats can be converted to [crate::Fixed]
+    #[error("invalid float {0}")]
+    InvalidFloat(String),
+}
+
+#[derive(Error, Debug)]
+pub enum AbiError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid abi json: {0}")]
+    InvalidJson(String),
+    /// A type name which is neither a core type nor a struct or enum of the ABI
+    #[error("unknown abi type {0}")]
+    UnknownType(String),
+    #[error("function {0} not found in the abi")]
+    UnknownFunction(String),
+    #[error("function {function} has no parameter {param}")]
+    UnknownParameter { function: String, param: String },
+    /// The layout of the rust type can't be reflected
+    #[error("invalid rust type, {0}")]
+    Layout(#[from] DecodeError),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum CalldataError {
+    /// The token at `index` of the text calldata isn't a decimal or `0x` prefixed hex number
+    #[error("invalid element {token:?} at index {index}")]
+    InvalidElement { index: usize, token: String },
+    /// The token at `index` is a number which is not below the field modulus
+    #[error("element {token} at index {index} out of range")]
+    OutOfRange { index: usize, token: String },
+    #[error("invalid calldata, {0}")]
+    Decode(#[from] DecodeError),
+}
+
+/// An invalid container of [crate::compact]
+#[derive(Error, Debug)]
+pub enum CompactError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid magic bytes")]
+    InvalidMagic,
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u8),
+    #[error("checksum mismatch, the container is corrupted")]
+    ChecksumMismatch,
+    /// A malformed field with a valid checksum, e.g. an element out of the field range
+    #[error("invalid container, {0}")]
+    Invalid(&'static str),
+}
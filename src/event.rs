@@ -0,0 +1,235 @@
+use crate::decoder::{Decode, DecoderImpl};
+use crate::der::de_owned::SerdeDecoder;
+use crate::der::reader::{Reader, SliceReader};
+use crate::error::DecodeError;
+use crate::proto::{Deposit, FullExit, OpType};
+use crate::{from_slice, U256};
+use serde::de::DeserializeOwned;
+use starknet::core::types::{EmittedEvent, FieldElement};
+use starknet::core::utils::get_selector_from_name;
+
+/// An event emitted by a cairo contract.
+///
+/// The first key of an emitted event is the selector of the event name, the following keys are
+/// the `#[key]` members and `data` contains all other members, both encoded like calldata.
+pub trait StarkEvent: Sized {
+    /// The name of the event in the contract
+    const NAME: &'static str;
+
+    /// The first key of the emitted event
+    fn selector() -> FieldElement {
+        get_selector_from_name(Self::NAME).expect("event name must be ascii")
+    }
+
+    /// Decode the event from its keys (without the selector) and data.
+    fn decode(keys: &[FieldElement], data: &[FieldElement]) -> Result<Self, DecodeError>;
+
+    /// Decode `event` if it's an event of this type, other events return `None`.
+    fn from_emitted_event(event: &EmittedEvent) -> Result<Option<Self>, DecodeError> {
+        match event.keys.split_first() {
+            Some((selector, keys)) if *selector == Self::selector() => {
+                Self::decode(keys, &event.data).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Decode the next value of type `T` from `decoder`, used by events whose data can't be
+/// described as one serde type.
+fn decode_next<T, R>(decoder: &mut DecoderImpl<R>) -> Result<T, DecodeError>
+where
+    T: DeserializeOwned,
+    R: Reader,
+{
+    T::deserialize(SerdeDecoder::new(decoder))
+}
+
+/// `BlockCommit(#[key] block_number: u64, #[key] commitment: u256)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockCommit {
+    pub block_number: u64,
+    pub commitment: U256,
+}
+
+impl StarkEvent for BlockCommit {
+    const NAME: &'static str = "BlockCommit";
+
+    fn decode(keys: &[FieldElement], _data: &[FieldElement]) -> Result<Self, DecodeError> {
+        let (block_number, commitment) = from_slice(keys)?;
+        Ok(Self {
+            block_number,
+            commitment,
+        })
+    }
+}
+
+/// `BlockExecuted(#[key] block_number: u64)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockExecuted {
+    pub block_number: u64,
+}
+
+impl StarkEvent for BlockExecuted {
+    const NAME: &'static str = "BlockExecuted";
+
+    fn decode(keys: &[FieldElement], _data: &[FieldElement]) -> Result<Self, DecodeError> {
+        let block_number = from_slice(keys)?;
+        Ok(Self { block_number })
+    }
+}
+
+/// The operation requested on l1 that must be processed by the rollup
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PriorityOperation {
+    Deposit(Deposit),
+    FullExit(FullExit),
+}
+
+impl PriorityOperation {
+    pub fn op_type(&self) -> OpType {
+        match self {
+            PriorityOperation::Deposit(_) => OpType::Deposit,
+            PriorityOperation::FullExit(_) => OpType::FullExit,
+        }
+    }
+}
+
+/// `NewPriorityRequest(sender: ContractAddress, serial_id: u64, op_type: u8, op: Deposit | FullExit, expiration_block: u64)`
+///
+/// The operation is encoded as its `proto` struct, which one is selected by `op_type`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewPriorityRequest {
+    pub sender: FieldElement,
+    pub serial_id: u64,
+    pub op: PriorityOperation,
+    pub expiration_block: u64,
+}
+
+impl StarkEvent for NewPriorityRequest {
+    const NAME: &'static str = "NewPriorityRequest";
+
+    fn decode(_keys: &[FieldElement], data: &[FieldElement]) -> Result<Self, DecodeError> {
+        let mut decoder = DecoderImpl::new(SliceReader::new(data));
+        let sender = FieldElement::decode(&mut decoder)?;
+        let serial_id = decode_next(&mut decoder)?;
+        let op_type: u8 = decode_next(&mut decoder)?;
+        let op = match OpType::try_from(op_type) {
+            Ok(OpType::Deposit) => PriorityOperation::Deposit(decode_next(&mut decoder)?),
+            Ok(OpType::FullExit) => PriorityOperation::FullExit(decode_next(&mut decoder)?),
+            _ => {
+                return Err(DecodeError::Other(format!(
+                    "op type {op_type} is not a priority operation"
+                )))
+            }
+        };
+        let expiration_block = decode_next(&mut decoder)?;
+        Ok(Self {
+            sender,
+            serial_id,
+            op,
+            expiration_block,
+        })
+    }
+}
+
+/// All the events of the zkLink contract tracked by the watcher
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ZkLinkEvent {
+    BlockCommit(BlockCommit),
+    BlockExecuted(BlockExecuted),
+    NewPriorityRequest(NewPriorityRequest),
+}
+
+impl ZkLinkEvent {
+    /// The keys filter that matches all the events of [ZkLinkEvent]
+    pub fn keys() -> Vec<Vec<FieldElement>> {
+        vec![vec![
+            BlockCommit::selector(),
+            BlockExecuted::selector(),
+            NewPriorityRequest::selector(),
+        ]]
+    }
+
+    /// Decode `event`, returns `None` if it's not a zkLink event.
+    pub fn from_emitted_event(event: &EmittedEvent) -> Result<Option<Self>, DecodeError> {
+        if let Some(e) = BlockCommit::from_emitted_event(event)? {
+            return Ok(Some(Self::BlockCommit(e)));
+        }
+        if let Some(e) = BlockExecuted::from_emitted_event(event)? {
+            return Ok(Some(Self::BlockExecuted(e)));
+        }
+        if let Some(e) = NewPriorityRequest::from_emitted_event(event)? {
+            return Ok(Some(Self::NewPriorityRequest(e)));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::num::PrimitiveU256;
+    use crate::to_field_elements;
+
+    fn emitted_event(keys: Vec<FieldElement>, data: Vec<FieldElement>) -> EmittedEvent {
+        EmittedEvent {
+            from_address: FieldElement::ONE,
+            keys,
+            data,
+            block_hash: FieldElement::ZERO,
+            block_number: 1,
+            transaction_hash: FieldElement::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_block_commit() {
+        let commitment: U256 = PrimitiveU256::from(5u8).into();
+        let mut keys = vec![BlockCommit::selector()];
+        keys.extend(to_field_elements((3u64, commitment)).unwrap());
+        let event = emitted_event(keys, vec![]);
+
+        let e = BlockCommit::from_emitted_event(&event).unwrap();
+        assert_eq!(
+            e,
+            Some(BlockCommit {
+                block_number: 3,
+                commitment
+            })
+        );
+        assert_eq!(BlockExecuted::from_emitted_event(&event).unwrap(), None);
+    }
+
+    #[test]
+    fn test_new_priority_request() {
+        let deposit = Deposit {
+            chain_id: 1,
+            account_id: 2,
+            sub_account_id: 0,
+            token_id: 3,
+            target_token_id: 3,
+            amount: 100,
+            owner: "9e290521bb937cebdbd1b5636037f089f7bf34de51f9fc019b07cdb8ed98a1".to_string(),
+        };
+        let mut data = vec![FieldElement::from(10u8)];
+        data.extend(to_field_elements((7u64, u8::from(OpType::Deposit), &deposit, 20u64)).unwrap());
+        let event = emitted_event(vec![NewPriorityRequest::selector()], data);
+
+        let e = ZkLinkEvent::from_emitted_event(&event).unwrap();
+        let expected = NewPriorityRequest {
+            sender: FieldElement::from(10u8),
+            serial_id: 7,
+            op: PriorityOperation::Deposit(deposit),
+            expiration_block: 20,
+        };
+        assert_eq!(e, Some(ZkLinkEvent::NewPriorityRequest(expected)));
+    }
+
+    #[test]
+    fn test_unknown_priority_op_type() {
+        let data = to_field_elements((10u8, 7u64, u8::from(OpType::Withdraw))).unwrap();
+        let event = emitted_event(vec![NewPriorityRequest::selector()], data);
+        assert!(NewPriorityRequest::from_emitted_event(&event).is_err());
+    }
+}
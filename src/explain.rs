@@ -0,0 +1,236 @@
+//! Annotate the elements of an encoding with the field they belong to, to debug calldata without
+//! counting the elements by hand.
+
+use crate::encoder::Encoder;
+use crate::error::{EncodeError, FieldPath, PathSegment};
+use crate::ser::SerdeEncoder;
+use serde::Serialize;
+use starknet::core::types::FieldElement;
+use std::fmt::{self, Display};
+
+/// An element of the encoding and the field it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainedElement {
+    /// The index of the element in the encoding
+    pub offset: usize,
+    /// The path of the field, e.g. `onchain_operations.len` for the length of a sequence
+    pub path: FieldPath,
+    /// The rust type of the field as seen by serde, e.g. `u64` for `usize` fields, or
+    /// `Enum::Variant` for the variant index of an enum
+    pub rust_type: String,
+    pub element: FieldElement,
+}
+
+impl Display for ExplainedElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {:#x}",
+            self.offset, self.path, self.rust_type, self.element
+        )
+    }
+}
+
+/// The explained elements of an encoding, displayed as a table with a row per element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Explanation(pub Vec<ExplainedElement>);
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<_> = self
+            .0
+            .iter()
+            .map(|e| {
+                [
+                    e.offset.to_string(),
+                    e.path.to_string(),
+                    e.rust_type.clone(),
+                    format!("{:#x}", e.element),
+                ]
+            })
+            .collect();
+        let header = ["offset", "path", "type", "felt"].map(String::from);
+        let mut widths = header.clone().map(|h| h.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        for row in std::iter::once(&header).chain(&rows) {
+            let [offset, path, rust_type, felt] = row;
+            writeln!(
+                f,
+                "{offset:>w0$}  {path:<w1$}  {rust_type:<w2$}  {felt}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An [Encoder] that records the path and type of each pushed element.
+#[derive(Default)]
+struct ExplainEncoder {
+    path: Vec<PathSegment>,
+    rust_type: String,
+    elements: Vec<ExplainedElement>,
+}
+
+impl Encoder for ExplainEncoder {
+    fn push_field_element(&mut self, element: FieldElement) {
+        self.elements.push(ExplainedElement {
+            offset: self.elements.len(),
+            path: FieldPath(self.path.clone()),
+            rust_type: self.rust_type.clone(),
+            element,
+        });
+    }
+
+    fn enter(&mut self, segment: PathSegment) {
+        self.path.push(segment);
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    fn wants_descriptions(&self) -> bool {
+        true
+    }
+
+    fn describe(&mut self, type_name: &str) {
+        self.rust_type = type_name.to_string();
+    }
+}
+
+/// Encode `value` like [crate::to_field_elements], with the path and rust type of each element.
+pub fn explain_field_elements<T>(value: &T) -> Result<Explanation, EncodeError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut encoder = ExplainEncoder::default();
+    value.serialize(SerdeEncoder::new(&mut encoder))?;
+    Ok(Explanation(encoder.elements))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::{Bytes, CommitBlockInfo, OnchainOperationData};
+    use crate::{to_field_elements, U256};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_explain_commit_block_info() {
+        let info = CommitBlockInfo {
+            new_state_hash: U256::from(7u8),
+            public_data: Bytes::from_bytes(&[1, 2, 3]),
+            timestamp: 10,
+            onchain_operations: vec![OnchainOperationData {
+                eth_witness: Bytes::default(),
+                public_data_offset: 2,
+            }],
+            block_number: 5,
+            fee_account: 3,
+        };
+        let explanation = explain_field_elements(&info).unwrap();
+        let elements: Vec<_> = explanation.0.iter().map(|e| e.element).collect();
+        assert_eq!(elements, to_field_elements(&info).unwrap());
+
+        let rows: Vec<_> = explanation.0.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rows,
+            [
+                "0 new_state_hash.low u128 0x7",
+                "1 new_state_hash.high u128 0x0",
+                "2 public_data.size u64 0x3",
+                "3 public_data.data.len usize 0x1",
+                "4 public_data.data[0] u128 0x1020300000000000000000000000000",
+                "5 timestamp u64 0xa",
+                "6 onchain_operations.len usize 0x1",
+                "7 onchain_operations[0].eth_witness.size u64 0x0",
+                "8 onchain_operations[0].eth_witness.data.len usize 0x0",
+                "9 onchain_operations[0].public_data_offset u64 0x2",
+                "10 block_number u64 0x5",
+                "11 fee_account u32 0x3",
+            ]
+        );
+        let table = explanation.to_string();
+        assert!(table.starts_with("offset  path"));
+        assert!(table.contains("\n     6  onchain_operations.len "));
+    }
+
+    #[test]
+    fn test_explain_enums_and_maps() {
+        #[derive(Serialize)]
+        enum Op {
+            Deposit { amount: u64 },
+        }
+        let value = (
+            Some(Op::Deposit { amount: 4 }),
+            BTreeMap::from([(1u8, 'a')]),
+        );
+        let rows: Vec<_> = explain_field_elements(&value)
+            .unwrap()
+            .0
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "0 [0] Option::Some 0x0",
+                "1 [0] Op::Deposit 0x0",
+                "2 [0].amount u64 0x4",
+                "3 [1].len usize 0x1",
+                "4 [1][0].key u8 0x1",
+                "5 [1][0].value char 0x61",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_hooks() {
+        /// Counts the calls of the hooks
+        #[derive(Default)]
+        struct Hooks {
+            depth: usize,
+            descriptions: usize,
+        }
+
+        impl Encoder for Hooks {
+            fn push_field_element(&mut self, _: FieldElement) {}
+
+            fn enter(&mut self, _: PathSegment) {
+                self.depth += 1;
+            }
+
+            fn leave(&mut self) {
+                self.depth -= 1;
+            }
+
+            fn describe(&mut self, _: &str) {
+                self.descriptions += 1;
+            }
+        }
+
+        // the failed fields are left
+        let mut hooks = Hooks::default();
+        let value = (1u8, ("a", 1.5f32));
+        assert!(value.serialize(SerdeEncoder::new(&mut hooks)).is_err());
+        assert_eq!(hooks.depth, 0);
+
+        // the encoder doesn't want descriptions, only the static ones are given
+        #[derive(Serialize)]
+        enum Op {
+            A,
+        }
+        let mut hooks = Hooks::default();
+        let value = (Some(Op::A), BTreeMap::from([(1u8, 'a')]));
+        value.serialize(SerdeEncoder::new(&mut hooks)).unwrap();
+        assert_eq!(hooks.depth, 0);
+        assert_eq!(hooks.descriptions, 4);
+    }
+}
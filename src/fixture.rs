@@ -0,0 +1,541 @@
+//! Cairo test fixtures generated from rust values, so the contract tests check the same layouts as
+//! the rust encoding.
+//!
+//! A [CairoFixture] renders a cairo test which deserializes the calldata of the value, compares it
+//! with the struct literal of the value and serializes it back. The fixtures of the [crate::proto]
+//! types are stored under `tests/vectors/` and verified by [verify_golden] on every test run, run
+//! the tests with `UPDATE_VECTORS=1` to rewrite them after a layout change.
+
+use crate::array::{FELTS_NAME, SPAN_NAME};
+use crate::error::{EncodeError, PathSegment};
+use crate::packed::BytesMode;
+use crate::to_field_elements;
+use serde::ser::{self, Serialize};
+use starknet::core::types::FieldElement;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+
+/// Lines longer than this are split, one item per line
+const MAX_LINE: usize = 80;
+
+/// The environment variable which makes [verify_golden] rewrite the files instead of comparing them
+pub const UPDATE_ENV: &str = "UPDATE_VECTORS";
+
+/// A cairo test of the encoding of a value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CairoFixture {
+    /// The name of the test, without the `test_` prefix
+    pub name: String,
+    /// The cairo type of the value, e.g. `StoredBlockInfo` or `Array<u256>`
+    pub cairo_type: String,
+    pub calldata: Vec<FieldElement>,
+    /// The cairo expression of the value, e.g. `u256 { low: 1, high: 0 }`
+    pub literal: String,
+}
+
+impl CairoFixture {
+    pub fn new<T: Serialize + ?Sized>(
+        name: &str,
+        cairo_type: &str,
+        value: &T,
+    ) -> Result<Self, EncodeError> {
+        Ok(Self {
+            name: name.to_string(),
+            cairo_type: cairo_type.to_string(),
+            calldata: to_field_elements(value)?,
+            literal: cairo_literal(value)?,
+        })
+    }
+}
+
+/// The cairo test function of the fixture
+impl Display for CairoFixture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elements = self.calldata.iter().map(|e| format!("{e:#x}")).collect();
+        let calldata = block("array![", elements, "]", false);
+        writeln!(f, "#[test]")?;
+        writeln!(f, "fn test_{}() {{", self.name)?;
+        writeln!(f, "    let calldata = {};", indent(&calldata))?;
+        writeln!(f, "    let expected = {};", indent(&self.literal))?;
+        writeln!(f, "    let mut span = calldata.span();")?;
+        writeln!(
+            f,
+            "    let value: {} = Serde::deserialize(ref span).unwrap();",
+            self.cairo_type
+        )?;
+        writeln!(f, "    assert(value == expected, 'deserialize mismatch');")?;
+        writeln!(f, "    assert(span.is_empty(), 'trailing elements');")?;
+        writeln!(f, "    let mut output = array![];")?;
+        writeln!(f, "    expected.serialize(ref output);")?;
+        writeln!(f, "    assert(output == calldata, 'serialize mismatch');")?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Compare `contents` with the golden file at `path`, or write it if [UPDATE_ENV] is set.
+///
+/// A missing file is an error, so a new vector must be written explicitly.
+pub fn verify_golden(path: impl AsRef<Path>, contents: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        return std::fs::write(path, contents);
+    }
+    let golden = std::fs::read_to_string(path).map_err(|e| {
+        let msg = format!("{}: {e}, set {UPDATE_ENV}=1 to write it", path.display());
+        io::Error::new(e.kind(), msg)
+    })?;
+    if golden == contents {
+        return Ok(());
+    }
+    let line = golden
+        .lines()
+        .zip(contents.lines())
+        .position(|(golden, found)| golden != found)
+        .unwrap_or_else(|| golden.lines().count().min(contents.lines().count()));
+    let msg = format!(
+        "{} differs at line {}, set {UPDATE_ENV}=1 to rewrite it",
+        path.display(),
+        line + 1
+    );
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// The cairo expression of `value`.
+pub fn cairo_literal<T: Serialize + ?Sized>(value: &T) -> Result<String, EncodeError> {
+    value.serialize(LiteralSerializer)
+}
+
+/// The cairo name of a rust struct
+fn cairo_name(name: &str) -> &str {
+    match name {
+        "U256" => "u256",
+        "U384" => "u384",
+        "U512" => "u512",
+        name => name,
+    }
+}
+
+/// Indent the lines after the first one.
+fn indent(s: &str) -> String {
+    s.replace('\n', "\n    ")
+}
+
+/// `head`, the items separated by commas then `tail`, on one line if it's short enough, with a
+/// space around the items if `pad`.
+fn block(head: &str, items: Vec<String>, tail: &str, pad: bool) -> String {
+    if items.is_empty() {
+        return format!("{head}{tail}");
+    }
+    let pad = if pad { " " } else { "" };
+    let line = format!("{head}{pad}{}{pad}{tail}", items.join(", "));
+    if line.len() <= MAX_LINE && !line.contains('\n') {
+        return line;
+    }
+    let mut lines = head.to_string();
+    for item in items {
+        lines.push_str(&format!("\n    {},", indent(&item)));
+    }
+    format!("{lines}\n{tail}")
+}
+
+fn unsupported(what: &str) -> EncodeError {
+    EncodeError::OtherString(format!("{what} has no cairo literal"))
+}
+
+/// A serializer of the cairo expression of a value
+struct LiteralSerializer;
+
+/// The items of a sequence, tuple or variant
+struct Items {
+    head: String,
+    items: Vec<String>,
+    tail: &'static str,
+}
+
+impl Items {
+    fn new(head: impl Into<String>, tail: &'static str) -> Self {
+        Self {
+            head: head.into(),
+            items: vec![],
+            tail,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.items.push(cairo_literal(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> String {
+        // a tuple of one item needs a trailing comma
+        if self.head == "(" && self.items.len() == 1 {
+            return format!("({},)", self.items[0]);
+        }
+        block(&self.head, self.items, self.tail, false)
+    }
+}
+
+/// The fields of a struct
+struct Fields {
+    name: &'static str,
+    fields: Vec<String>,
+}
+
+impl ser::Serializer for LiteralSerializer {
+    type Ok = String;
+    type Error = EncodeError;
+    type SerializeSeq = Items;
+    type SerializeTuple = Items;
+    type SerializeTupleStruct = Items;
+    type SerializeTupleVariant = Items;
+    type SerializeMap = ser::Impossible<String, EncodeError>;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = ser::Impossible<String, EncodeError>;
+
+    // the layout of the encoding, e.g. the limbs of `U256`
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, EncodeError> {
+        Err(unsupported("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, EncodeError> {
+        Err(unsupported("f64"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, EncodeError> {
+        Ok(u32::from(v).to_string())
+    }
+
+    // strings are the hex of a single element
+    fn serialize_str(self, v: &str) -> Result<String, EncodeError> {
+        let element =
+            FieldElement::from_hex_be(v).map_err(|_| EncodeError::InvalidString(v.to_string()))?;
+        Ok(format!("{element:#x}"))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<String, EncodeError> {
+        let bytes = v.iter().map(u8::to_string).collect();
+        Ok(block("array![", bytes, "]", false))
+    }
+
+    fn serialize_none(self) -> Result<String, EncodeError> {
+        Ok("Option::None".to_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, EncodeError> {
+        Ok(format!("Option::Some({})", cairo_literal(value)?))
+    }
+
+    fn serialize_unit(self) -> Result<String, EncodeError> {
+        Ok("()".to_string())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<String, EncodeError> {
+        Ok(format!("{} {{}}", cairo_name(name)))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, EncodeError> {
+        Ok(format!("{name}::{variant}"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<String, EncodeError> {
+        if name == SPAN_NAME {
+            return Ok(format!("{}.span()", cairo_literal(value)?));
+        }
+        if name == FELTS_NAME || BytesMode::from_newtype_name(name).is_some() {
+            return Err(unsupported(name));
+        }
+        cairo_literal(value)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<String, EncodeError> {
+        Ok(format!("{name}::{variant}({})", cairo_literal(value)?))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Items, EncodeError> {
+        Ok(Items::new("array![", "]"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Items, EncodeError> {
+        Ok(Items::new("(", ")"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Items, EncodeError> {
+        Ok(Items::new("(", ")"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Items, EncodeError> {
+        // the payload of a cairo variant is a tuple
+        Ok(Items::new(format!("{name}::{variant}(("), "))"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, EncodeError> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Fields, EncodeError> {
+        Ok(Fields {
+            name: cairo_name(name),
+            fields: vec![],
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(unsupported(&format!("struct variant {name}::{variant}")))
+    }
+}
+
+impl ser::SerializeSeq for Items {
+    type Ok = String;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<String, EncodeError> {
+        Ok(Items::end(self))
+    }
+}
+
+impl ser::SerializeTuple for Items {
+    type Ok = String;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<String, EncodeError> {
+        Ok(Items::end(self))
+    }
+}
+
+impl ser::SerializeTupleStruct for Items {
+    type Ok = String;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<String, EncodeError> {
+        Ok(Items::end(self))
+    }
+}
+
+impl ser::SerializeTupleVariant for Items {
+    type Ok = String;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<String, EncodeError> {
+        Ok(Items::end(self))
+    }
+}
+
+impl ser::SerializeStruct for Fields {
+    type Ok = String;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        let value = cairo_literal(value).map_err(|e| e.in_field(PathSegment::Field(key)))?;
+        self.fields.push(format!("{key}: {value}"));
+        Ok(())
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), EncodeError> {
+        Err(EncodeError::SkippedField(key))
+    }
+
+    fn end(self) -> Result<String, EncodeError> {
+        Ok(block(&format!("{} {{", self.name), self.fields, "}", true))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::array::{Felt, Span};
+    use crate::proto::{
+        Bytes, CommitBlockInfo, CompressedBlockExtraInfo, ExecuteBlockInfo, OnchainOperationData,
+        StoredBlockInfo,
+    };
+    use crate::U256;
+
+    fn stored_block_info() -> StoredBlockInfo {
+        StoredBlockInfo {
+            block_number: 10,
+            priority_operations: 2,
+            pending_onchain_operations_hash: U256::from(3u8),
+            timestamp: 1700000000,
+            state_hash: U256::new(4, 5),
+            commitment: U256::from(6u8),
+            sync_hash: U256::new(u128::MAX, 7),
+        }
+    }
+
+    fn commit_block_info() -> CommitBlockInfo {
+        CommitBlockInfo {
+            new_state_hash: U256::from(7u8),
+            public_data: Bytes::from_bytes(&(1..=20).collect::<Vec<u8>>()),
+            timestamp: 1700000010,
+            onchain_operations: vec![OnchainOperationData {
+                eth_witness: Bytes::from_bytes(&[0xee; 3]),
+                public_data_offset: 0,
+            }],
+            block_number: 11,
+            fee_account: 3,
+        }
+    }
+
+    #[test]
+    fn test_cairo_literal() {
+        assert_eq!(
+            cairo_literal(&U256::new(1, 2)).unwrap(),
+            "u256 { low: 1, high: 2 }"
+        );
+        assert_eq!(
+            cairo_literal(&(Some(3u8), None::<u8>, (true,))).unwrap(),
+            "(Option::Some(3), Option::None, (true,))"
+        );
+        assert_eq!(
+            cairo_literal(&Span(vec![Felt(FieldElement::from(26u8))])).unwrap(),
+            "array![0x1a].span()"
+        );
+        assert_eq!(
+            cairo_literal(&OnchainOperationData::default()).unwrap(),
+            "OnchainOperationData {
+    eth_witness: Bytes { size: 0, data: array![] },
+    public_data_offset: 0,
+}"
+        );
+        assert!(cairo_literal(&std::collections::BTreeMap::<u8, u8>::new()).is_err());
+
+        let literal = cairo_literal(&stored_block_info()).unwrap();
+        assert!(literal.starts_with("StoredBlockInfo {\n    block_number: 10,\n"));
+        assert!(literal.ends_with(
+            "    sync_hash: u256 { low: 340282366920938463463374607431768211455, high: 7 },\n}"
+        ));
+    }
+
+    #[test]
+    fn test_proto_vectors() {
+        let fixtures = [
+            CairoFixture::new("stored_block_info", "StoredBlockInfo", &stored_block_info()),
+            CairoFixture::new("commit_block_info", "CommitBlockInfo", &commit_block_info()),
+            CairoFixture::new(
+                "compressed_block_extra_info",
+                "CompressedBlockExtraInfo",
+                &CompressedBlockExtraInfo {
+                    public_data_hash: U256::from(1u8),
+                    offset_commitment_hash: U256::from(2u8),
+                    onchain_operation_pubdata_hashs: vec![U256::from(3u8), U256::from(4u8)],
+                },
+            ),
+            CairoFixture::new(
+                "execute_block_info",
+                "ExecuteBlockInfo",
+                &ExecuteBlockInfo {
+                    stored_block_info: stored_block_info(),
+                    pending_onchain_ops_pubdata: vec![Bytes::from_bytes(&[9; 17])],
+                },
+            ),
+        ];
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+        for fixture in fixtures {
+            let fixture = fixture.unwrap();
+            let path = dir.join(format!("{}.cairo", fixture.name));
+            verify_golden(path, &fixture.to_string()).unwrap();
+        }
+    }
+}
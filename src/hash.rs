@@ -0,0 +1,445 @@
+//! Off-chain versions of the hashes computed by the zkLink contract, so that a block can be
+//! validated before paying for its commit.
+//!
+//! Numbers are hashed as 32 bytes big-endian words and [Bytes] are hashed as their `size` bytes,
+//! without the zero padding of their last word.
+//!
+//! `hashStoredBlockInfo` is keccak256 over the fields as words, [pedersen_hash_stored_block_info]
+//! and [poseidon_hash_stored_block_info] are its variants over the encoded fields. The pedersen
+//! and poseidon hashes of other encoded values are computed by [pedersen_hash_of] and
+//! [poseidon_hash_of].
+use crate::encoder::Encoder;
+use crate::error::{EncodeError, PubdataError};
+use crate::proto::{Bytes, CommitBlockInfo, OpType, StoredBlockInfo};
+use crate::pubdata::{Operation, CHUNK_BYTES};
+use crate::ser::SerdeEncoder;
+use crate::{to_field_elements, U256};
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+use starknet::core::crypto::pedersen_hash;
+use starknet::core::types::FieldElement;
+use starknet_crypto::PoseidonHasher;
+
+pub fn keccak256(data: &[u8]) -> U256 {
+    U256::from_be_bytes(Keccak256::digest(data).into())
+}
+
+/// The keccak256 of empty bytes, the initial value of the onchain operations hash chain
+pub fn empty_string_keccak() -> U256 {
+    keccak256(&[])
+}
+
+fn u256_word(value: U256) -> [u8; 32] {
+    value.to_be_bytes()
+}
+
+fn u64_word(value: u64) -> [u8; 32] {
+    U256::from(value).to_be_bytes()
+}
+
+/// Hash of the concatenation of `words`
+fn hash_words(words: &[[u8; 32]]) -> U256 {
+    keccak256(&words.concat())
+}
+
+/// `hashStoredBlockInfo` of the contract, the keccak256 of all the fields as 32 bytes words.
+pub fn hash_stored_block_info(info: &StoredBlockInfo) -> U256 {
+    hash_words(&[
+        u64_word(info.block_number),
+        u64_word(info.priority_operations),
+        u256_word(info.pending_onchain_operations_hash),
+        u64_word(info.timestamp),
+        u256_word(info.state_hash),
+        u256_word(info.commitment),
+        u256_word(info.sync_hash),
+    ])
+}
+
+/// `hashStoredBlockInfo` of the contract hashing with pedersen, the chain of pedersen hashes of the
+/// encoded fields from zero like `PedersenTrait::new(0).update_with(info).finalize()`, so unlike
+/// [pedersen_hash_of] the number of elements isn't hashed.
+pub fn pedersen_hash_stored_block_info(info: &StoredBlockInfo) -> FieldElement {
+    let elements = to_field_elements(info).expect("StoredBlockInfo is encodable");
+    elements.iter().fold(FieldElement::ZERO, |state, element| {
+        pedersen_hash(&state, element)
+    })
+}
+
+/// `hashStoredBlockInfo` of the contract hashing with poseidon, the hash of the encoded fields like
+/// `PoseidonTrait::new().update_with(info).finalize()`.
+pub fn poseidon_hash_stored_block_info(info: &StoredBlockInfo) -> FieldElement {
+    poseidon_hash_of(info).expect("StoredBlockInfo is encodable")
+}
+
+/// Append the pubdata of an onchain operation to the onchain operations hash chain.
+pub fn onchain_ops_hash(prev_hash: U256, op_pubdata: &[u8]) -> U256 {
+    let mut data = u256_word(prev_hash).to_vec();
+    data.extend_from_slice(op_pubdata);
+    keccak256(&data)
+}
+
+/// One byte per pubdata chunk, which is 1 if an onchain operation starts at the chunk.
+pub fn offsets_commitment(block: &CommitBlockInfo) -> Result<Vec<u8>, PubdataError> {
+    block.public_data.validate()?;
+    let mut offsets = vec![0u8; block.public_data.size / CHUNK_BYTES];
+    for op in &block.onchain_operations {
+        let offset = op.public_data_offset;
+        if offset % CHUNK_BYTES != 0 || offset / CHUNK_BYTES >= offsets.len() {
+            return Err(PubdataError::InvalidOffset(offset));
+        }
+        offsets[offset / CHUNK_BYTES] = 1;
+    }
+    Ok(offsets)
+}
+
+/// The commitment of a new block, which is the public input of the block proof.
+pub fn block_commitment(
+    previous_block: &StoredBlockInfo,
+    block: &CommitBlockInfo,
+) -> Result<U256, PubdataError> {
+    let offsets = offsets_commitment(block)?;
+    let mut commitment = hash_words(&[
+        u64_word(block.block_number),
+        u64_word(block.fee_account as u64),
+    ]);
+    commitment = hash_words(&[u256_word(commitment), u256_word(previous_block.state_hash)]);
+    commitment = hash_words(&[u256_word(commitment), u256_word(block.new_state_hash)]);
+    commitment = hash_words(&[u256_word(commitment), u64_word(block.timestamp)]);
+
+    let mut data = u256_word(commitment).to_vec();
+    data.extend(block.public_data.to_vec());
+    data.extend(offsets);
+    Ok(keccak256(&data))
+}
+
+/// The sync hash of a block used for cross chain block verify.
+pub fn sync_hash(pre_sync_hash: U256, commitment: U256) -> U256 {
+    hash_words(&[u256_word(pre_sync_hash), u256_word(commitment)])
+}
+
+/// Collect the onchain operations of a block, returns the number of priority operations and the
+/// hash chain of the operations that must be processed after verify.
+pub fn collect_onchain_ops(block: &CommitBlockInfo) -> Result<(u64, U256), PubdataError> {
+    offsets_commitment(block)?;
+    let pubdata = block.public_data.to_vec();
+    let mut priority_operations = 0;
+    let mut pending_hash = empty_string_keccak();
+    for op in &block.onchain_operations {
+        let offset = op.public_data_offset;
+        let op_type = pubdata[offset];
+        let end = offset + Operation::chunks(op_type)? * CHUNK_BYTES;
+        let op_pubdata = pubdata
+            .get(offset..end)
+            .ok_or(PubdataError::LengthMismatch {
+                required: end,
+                found: pubdata.len(),
+            })?;
+        match OpType::try_from(op_type) {
+            Ok(OpType::Deposit) => priority_operations += 1,
+            Ok(OpType::FullExit) => {
+                priority_operations += 1;
+                pending_hash = onchain_ops_hash(pending_hash, op_pubdata);
+            }
+            Ok(OpType::Withdraw) | Ok(OpType::ForcedExit) => {
+                pending_hash = onchain_ops_hash(pending_hash, op_pubdata);
+            }
+            Ok(OpType::ChangePubKey) => {}
+            _ => return Err(PubdataError::UnsupportedOpType(op_type)),
+        }
+    }
+    Ok((priority_operations, pending_hash))
+}
+
+/// The [StoredBlockInfo] that the contract will store after committing `block`.
+pub fn commit_block(
+    previous_block: &StoredBlockInfo,
+    block: &CommitBlockInfo,
+) -> Result<StoredBlockInfo, PubdataError> {
+    let (priority_operations, pending_onchain_operations_hash) = collect_onchain_ops(block)?;
+    let commitment = block_commitment(previous_block, block)?;
+    Ok(StoredBlockInfo {
+        block_number: block.block_number,
+        priority_operations,
+        pending_onchain_operations_hash,
+        timestamp: block.timestamp,
+        state_hash: block.new_state_hash,
+        commitment,
+        sync_hash: sync_hash(previous_block.sync_hash, commitment),
+    })
+}
+
+/// The keccak256 of the pubdata stored in [Bytes].
+pub fn hash_bytes(bytes: &Bytes) -> Result<U256, PubdataError> {
+    bytes.validate()?;
+    Ok(keccak256(&bytes.to_vec()))
+}
+
+/// An [Encoder] that hashes the encoded elements instead of collecting them, the result is the
+/// same as `compute_hash_on_elements` which appends the number of elements to the hash chain.
+#[derive(Debug, Clone)]
+pub struct PedersenEncoder {
+    state: FieldElement,
+    len: usize,
+}
+
+impl PedersenEncoder {
+    pub fn new() -> Self {
+        Self {
+            state: FieldElement::ZERO,
+            len: 0,
+        }
+    }
+
+    /// Hash the encoded elements of `value`.
+    pub fn update<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(SerdeEncoder::new(self))
+    }
+
+    pub fn finalize(self) -> FieldElement {
+        pedersen_hash(&self.state, &FieldElement::from(self.len))
+    }
+}
+
+impl Default for PedersenEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder for PedersenEncoder {
+    fn push_field_element(&mut self, element: FieldElement) {
+        self.state = pedersen_hash(&self.state, &element);
+        self.len += 1;
+    }
+}
+
+/// An [Encoder] that hashes the encoded elements instead of collecting them, the result is the
+/// same as `poseidon_hash_many`.
+#[derive(Default)]
+pub struct PoseidonEncoder {
+    hasher: PoseidonHasher,
+}
+
+impl PoseidonEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash the encoded elements of `value`.
+    pub fn update<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(SerdeEncoder::new(self))
+    }
+
+    pub fn finalize(self) -> FieldElement {
+        self.hasher.finalize()
+    }
+}
+
+impl Encoder for PoseidonEncoder {
+    fn push_field_element(&mut self, element: FieldElement) {
+        self.hasher.update(element);
+    }
+}
+
+/// The pedersen hash of the encoded elements of `value` computed by `compute_hash_on_elements`.
+pub fn pedersen_hash_of<T: Serialize + ?Sized>(value: &T) -> Result<FieldElement, EncodeError> {
+    let mut encoder = PedersenEncoder::new();
+    encoder.update(value)?;
+    Ok(encoder.finalize())
+}
+
+/// The poseidon hash of the encoded elements of `value` computed by `poseidon_hash_many`.
+pub fn poseidon_hash_of<T: Serialize + ?Sized>(value: &T) -> Result<FieldElement, EncodeError> {
+    let mut encoder = PoseidonEncoder::new();
+    encoder.update(value)?;
+    Ok(encoder.finalize())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::num::PrimitiveU256;
+    use crate::proto::{Deposit, OnchainOperationData, Withdraw};
+    use starknet::core::crypto::compute_hash_on_elements;
+    use starknet_crypto::poseidon_hash_many;
+
+    fn u256(s: &str) -> U256 {
+        PrimitiveU256::from_str_radix(s, 16).unwrap().into()
+    }
+
+    pub(crate) fn stored_block_info() -> StoredBlockInfo {
+        StoredBlockInfo {
+            block_number: 1,
+            priority_operations: 2,
+            pending_onchain_operations_hash: PrimitiveU256::from(3u8).into(),
+            timestamp: 4,
+            state_hash: PrimitiveU256::from(5u8).into(),
+            commitment: PrimitiveU256::from(6u8).into(),
+            sync_hash: PrimitiveU256::from(7u8).into(),
+        }
+    }
+
+    pub(crate) fn commit_block_info() -> CommitBlockInfo {
+        let owner = "9e290521bb937cebdbd1b5636037f089f7bf34de51f9fc019b07cdb8ed98a1";
+        let ops = vec![
+            Operation::Deposit(Deposit {
+                chain_id: 1,
+                account_id: 2,
+                sub_account_id: 0,
+                token_id: 3,
+                target_token_id: 3,
+                amount: 100,
+                owner: owner.to_string(),
+            }),
+            Operation::Noop,
+            Operation::Withdraw(Withdraw {
+                chain_id: 1,
+                account_id: 2,
+                token_id: 3,
+                amount: 50,
+                owner: owner.to_string(),
+                nonce: 0,
+                fast_withdraw_fee_rate: 0,
+            }),
+        ];
+        CommitBlockInfo {
+            new_state_hash: PrimitiveU256::from(8u8).into(),
+            public_data: Operation::to_block_bytes(&ops).unwrap(),
+            timestamp: 9,
+            onchain_operations: vec![
+                OnchainOperationData {
+                    eth_witness: Bytes::default(),
+                    public_data_offset: 0,
+                },
+                OnchainOperationData {
+                    eth_witness: Bytes::default(),
+                    public_data_offset: 4 * CHUNK_BYTES,
+                },
+            ],
+            block_number: 2,
+            fee_account: 10,
+        }
+    }
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            empty_string_keccak(),
+            u256("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+    }
+
+    // Regression vectors of this module, they are checked against `hashStoredBlockInfo` of the
+    // contract by `client::test::test_hash_stored_block_info` on a devnet.
+    #[test]
+    fn test_hash_stored_block_info() {
+        assert_eq!(
+            hash_stored_block_info(&stored_block_info()),
+            u256("dc0f375e737e67a9da47e3f3d3caafe3435f5bcf1bb0980453eedbd45cd33547")
+        );
+    }
+
+    // Regression vectors as well, checked against the contract by the same devnet test.
+    #[test]
+    fn test_hash_stored_block_info_variants() {
+        let info = stored_block_info();
+        let felt = |s| FieldElement::from_hex_be(s).unwrap();
+        assert_eq!(
+            pedersen_hash_stored_block_info(&info),
+            felt("0x3a8cf5f70c692c3ec4614113220379f2ca9d97543b60c856fad6d3395b63f")
+        );
+        assert_eq!(
+            poseidon_hash_stored_block_info(&info),
+            felt("0x2b156e6e0ccdaccbf9b144c553087d4e74de8a4834716f84b1d0d0391a67ee5")
+        );
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        // 3 bytes in a 16 bytes word, the padding isn't hashed
+        let bytes = Bytes::from_bytes(&[1, 2, 3]);
+        assert_eq!(
+            hash_bytes(&bytes).unwrap(),
+            u256("f1885eda54b7a053318cd41e2093220dab15d65381b1157a3633a83bfd5c9239")
+        );
+        let mut padded = [0u8; 16];
+        padded[..3].copy_from_slice(&[1, 2, 3]);
+        assert_ne!(hash_bytes(&bytes).unwrap(), keccak256(&padded));
+    }
+
+    // Regression vectors as well, `commitBlock` of the contract is checked against [commit_block]
+    // by the same devnet test.
+    #[test]
+    fn test_commit_block() {
+        let block = commit_block_info();
+        assert_eq!(
+            offsets_commitment(&block).unwrap(),
+            vec![1, 0, 0, 0, 1, 0, 0]
+        );
+        let info = commit_block(&stored_block_info(), &block).unwrap();
+        assert_eq!(info.priority_operations, 1);
+        assert_eq!(
+            info.pending_onchain_operations_hash,
+            u256("0cea0985639a129058bd3dee4ef2dbb06a02c7905078301542881175773006c0")
+        );
+        assert_eq!(
+            info.commitment,
+            u256("4db4de8a91003310d6b9842711e7e14db0a01b0b471ccc67c677424267424d7b")
+        );
+        assert_eq!(
+            info.sync_hash,
+            u256("c3bca34b7dc8d1d4127ad6b0e782f3fa5b30bc4785b38e87810be5be670d1543")
+        );
+        assert_eq!(info.state_hash, block.new_state_hash);
+    }
+
+    #[test]
+    fn test_invalid_offset() {
+        let mut block = commit_block_info();
+        block.onchain_operations[1].public_data_offset = 1;
+        assert_eq!(
+            commit_block(&stored_block_info(), &block),
+            Err(PubdataError::InvalidOffset(1))
+        );
+        block.onchain_operations[1].public_data_offset = 7 * CHUNK_BYTES;
+        assert!(commit_block(&stored_block_info(), &block).is_err());
+    }
+
+    #[test]
+    fn test_pedersen_hash_of() {
+        // compute_hash_on_elements(&[])
+        let empty = FieldElement::from_hex_be(
+            "0x49ee3eba8c1600700ee1b87eb599f16716b0b1022947733551fde4050ca6804",
+        )
+        .unwrap();
+        assert_eq!(pedersen_hash_of(&[0u8; 0]).unwrap(), empty);
+
+        let info = stored_block_info();
+        let elements = to_field_elements(&info).unwrap();
+        assert_eq!(
+            pedersen_hash_of(&info).unwrap(),
+            compute_hash_on_elements(&elements)
+        );
+
+        let mut encoder = PedersenEncoder::new();
+        encoder.update(&info).unwrap();
+        encoder.update(&vec![1u8, 2]).unwrap();
+        let mut elements = to_field_elements(&info).unwrap();
+        elements.extend(to_field_elements(vec![1u8, 2]).unwrap());
+        assert_eq!(encoder.finalize(), compute_hash_on_elements(&elements));
+    }
+
+    #[test]
+    fn test_poseidon_hash_of() {
+        let block = commit_block_info();
+        let elements = to_field_elements(&block).unwrap();
+        assert_eq!(
+            poseidon_hash_of(&block).unwrap(),
+            poseidon_hash_many(&elements)
+        );
+        assert_eq!(
+            poseidon_hash_of(&[0u8; 0]).unwrap(),
+            poseidon_hash_many(&[])
+        );
+    }
+}
@@ -0,0 +1,830 @@
+//! The layout of the encoding of a type, reflected from its `Deserialize` impl, to know how many
+//! elements a value takes without encoding one.
+//!
+//! The layout is traced by deserializing placeholder values: sequences and maps are traced with
+//! their element, entry and variant types, and enums are traced once per variant.
+
+use crate::array::{FELTS_NAME, SPAN_NAME};
+use crate::error::DecodeError;
+use crate::packed::BytesMode;
+use crate::serde::NATIVE_NAME;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, Expected, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+use serde::Deserializer;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// The structure of the encoding of a type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// A single element, with the rust type seen by serde, e.g. `u64` for `usize`, or `felt252`
+    /// for strings
+    Felt(&'static str),
+    /// The fields in order, structs without fields and `()` take no element
+    Struct {
+        name: &'static str,
+        fields: Vec<(&'static str, Layout)>,
+    },
+    /// The elements of a tuple, tuple struct or fixed size array in order
+    Tuple(Vec<Layout>),
+    /// The length, then the elements
+    Seq(Box<Layout>),
+    /// A [crate::array::Span], encoded like [Layout::Seq]
+    Span(Box<Layout>),
+    /// The length, then the entries ordered by key, the value of a set is `()`
+    Map {
+        key: Box<Layout>,
+        value: Box<Layout>,
+    },
+    /// The index of the variant, then its fields. `Option` is `{ Some: T, None: () }`
+    Enum {
+        name: &'static str,
+        variants: Vec<(&'static str, Layout)>,
+    },
+    /// The full words, the pending word and its length of a `ByteArray`, see
+    /// [crate::packed::bytes31]
+    Bytes31,
+    /// The length, the number of words, then the bytes packed into 16 bytes words, see
+    /// [crate::packed::bytes16]
+    Bytes16,
+    /// The remaining elements, see [crate::array::Felts]
+    Remaining,
+    /// The elements of a type with its own encoding, of which only the number taken by the
+    /// smallest value is known, see [crate::serde::Native]
+    Native { min_len: usize },
+}
+
+impl Layout {
+    const UNIT: Layout = Layout::Tuple(vec![]);
+
+    /// The number of elements if it's the same for every value.
+    pub fn fixed_len(&self) -> Option<usize> {
+        match self {
+            Self::Felt(_) => Some(1),
+            Self::Struct { fields, .. } => fields.iter().map(|(_, l)| l.fixed_len()).sum(),
+            Self::Tuple(items) => items.iter().map(Layout::fixed_len).sum(),
+            Self::Enum { variants, .. } => {
+                let mut lens = variants.iter().map(|(_, l)| l.fixed_len());
+                let first = lens.next()??;
+                lens.all(|len| len == Some(first)).then_some(1 + first)
+            }
+            Self::Seq(_)
+            | Self::Span(_)
+            | Self::Map { .. }
+            | Self::Bytes31
+            | Self::Bytes16
+            | Self::Remaining
+            | Self::Native { .. } => None,
+        }
+    }
+
+    /// Whether every value takes [Self::fixed_len] elements.
+    pub fn is_fixed_size(&self) -> bool {
+        self.fixed_len().is_some()
+    }
+
+    /// The number of elements of the smallest value, e.g. an empty `Vec` takes its length.
+    pub fn min_len(&self) -> usize {
+        match self {
+            Self::Felt(_) | Self::Seq(_) | Self::Span(_) | Self::Map { .. } => 1,
+            Self::Bytes16 => 2,
+            Self::Bytes31 => 3,
+            Self::Remaining => 0,
+            Self::Native { min_len } => *min_len,
+            Self::Struct { fields, .. } => fields.iter().map(|(_, l)| l.min_len()).sum(),
+            Self::Tuple(items) => items.iter().map(Layout::min_len).sum(),
+            Self::Enum { variants, .. } => {
+                1 + variants.iter().map(|(_, l)| l.min_len()).min().unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// Displayed like the cairo declaration of the type, e.g.
+/// `StoredBlockInfo { block_number: u64, state_hash: U256 { low: u128, high: u128 } }`.
+impl Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn fields(f: &mut fmt::Formatter<'_>, fields: &[(&str, Layout)]) -> fmt::Result {
+            write!(f, " {{")?;
+            for (i, (name, layout)) in fields.iter().enumerate() {
+                let sep = if i == 0 { " " } else { ", " };
+                write!(f, "{sep}{name}: {layout}")?;
+            }
+            write!(f, " }}")
+        }
+
+        match self {
+            Self::Felt(name) => write!(f, "{name}"),
+            Self::Struct { name, fields: f_ } if f_.is_empty() => write!(f, "{name}"),
+            Self::Struct { name, fields: f_ } | Self::Enum { name, variants: f_ } => {
+                write!(f, "{name}")?;
+                fields(f, f_)
+            }
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{sep}{item}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Seq(element) => write!(f, "Array<{element}>"),
+            Self::Span(element) => write!(f, "Span<{element}>"),
+            Self::Map { key, value } => write!(f, "Map<{key}, {value}>"),
+            Self::Bytes31 => write!(f, "ByteArray"),
+            Self::Bytes16 => write!(f, "Bytes16Packed"),
+            Self::Remaining => write!(f, "Felts"),
+            Self::Native { .. } => write!(f, "Native"),
+        }
+    }
+}
+
+/// A type whose [Layout] is known without a value.
+pub trait FeltLayout {
+    /// The layout of the encoding of the type, an error if it can't be decoded.
+    fn felt_layout() -> Result<Layout, DecodeError>;
+}
+
+impl<T: DeserializeOwned> FeltLayout for T {
+    fn felt_layout() -> Result<Layout, DecodeError> {
+        let mut registry = Registry::default();
+        let mut traced = 0;
+        loop {
+            let mut layout = Layout::UNIT;
+            T::deserialize(Tracer::new(&mut registry, &mut layout))?;
+            let untraced = registry.untraced();
+            if untraced.is_none() {
+                return registry.resolve(layout, &mut vec![]);
+            }
+            // every pass traces a new variant of the enums it meets
+            let now = registry.traced();
+            if now == traced {
+                let name = untraced.unwrap_or_default();
+                return Err(DecodeError::NotSupport(format!("recursive enum {name}")));
+            }
+            traced = now;
+        }
+    }
+}
+
+/// Deeper values are assumed to be of a recursive type
+const MAX_DEPTH: usize = 64;
+
+/// The state of the tracing, over the passes.
+#[derive(Default)]
+struct Registry {
+    /// The variants of the enums by name, and the layout of those which were traced
+    enums: BTreeMap<&'static str, (&'static [&'static str], Vec<Option<Layout>>)>,
+    /// The variants being traced, a nested enum of the same type picks another variant
+    in_progress: Vec<(&'static str, usize)>,
+    depth: usize,
+}
+
+impl Registry {
+    /// Pick an untraced variant of the enum, or a traced one if all variants were traced.
+    fn pick_variant(
+        &mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+    ) -> Result<usize, DecodeError> {
+        let (_, traced) = self
+            .enums
+            .entry(name)
+            .or_insert_with(|| (variants, vec![None; variants.len()]));
+        let untraced = (0..traced.len())
+            .find(|i| traced[*i].is_none() && !self.in_progress.contains(&(name, *i)));
+        untraced
+            .or_else(|| traced.iter().position(Option::is_some))
+            .ok_or_else(|| DecodeError::NotSupport(format!("recursive enum {name}")))
+    }
+
+    /// The number of traced variants
+    fn traced(&self) -> usize {
+        let traced = self.enums.values().flat_map(|(_, traced)| traced);
+        traced.filter(|layout| layout.is_some()).count()
+    }
+
+    /// The name of an enum with untraced variants
+    fn untraced(&self) -> Option<&'static str> {
+        self.enums
+            .iter()
+            .find(|(_, (_, traced))| traced.iter().any(Option::is_none))
+            .map(|(name, _)| *name)
+    }
+
+    /// Fill the variants of the enums of `layout`.
+    fn resolve(
+        &self,
+        layout: Layout,
+        enums: &mut Vec<&'static str>,
+    ) -> Result<Layout, DecodeError> {
+        let mut resolve = |layout| self.resolve(layout, enums);
+        Ok(match layout {
+            Layout::Struct { name, fields } => Layout::Struct {
+                name,
+                fields: fields
+                    .into_iter()
+                    .map(|(name, layout)| Ok((name, resolve(layout)?)))
+                    .collect::<Result<_, DecodeError>>()?,
+            },
+            Layout::Tuple(items) => Layout::Tuple(
+                items
+                    .into_iter()
+                    .map(resolve)
+                    .collect::<Result<_, DecodeError>>()?,
+            ),
+            Layout::Seq(element) => Layout::Seq(Box::new(resolve(*element)?)),
+            Layout::Span(element) => Layout::Span(Box::new(resolve(*element)?)),
+            Layout::Map { key, value } => Layout::Map {
+                key: Box::new(resolve(*key)?),
+                value: Box::new(resolve(*value)?),
+            },
+            Layout::Enum { name, variants } if variants.is_empty() => {
+                if enums.contains(&name) {
+                    return Err(DecodeError::NotSupport(format!("recursive enum {name}")));
+                }
+                let (names, traced) = &self.enums[name];
+                enums.push(name);
+                let variants = names
+                    .iter()
+                    .zip(traced)
+                    .map(|(variant, layout)| {
+                        let layout = layout.clone().expect("all variants are traced");
+                        Ok((*variant, self.resolve(layout, enums)?))
+                    })
+                    .collect::<Result<_, DecodeError>>()?;
+                enums.pop();
+                Layout::Enum { name, variants }
+            }
+            Layout::Enum { name, variants } => Layout::Enum {
+                name,
+                variants: variants
+                    .into_iter()
+                    .map(|(name, layout)| Ok((name, resolve(layout)?)))
+                    .collect::<Result<_, DecodeError>>()?,
+            },
+            layout => layout,
+        })
+    }
+
+    /// Trace the `len` fields of a struct or elements of a tuple.
+    fn elements<'de, V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<(V::Value, Vec<Layout>), DecodeError> {
+        let mut access = Elements {
+            registry: self,
+            len,
+            layouts: vec![],
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        Ok((value, access.layouts))
+    }
+
+    /// Trace `seed` as a nested value.
+    fn trace<'de, S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<(S::Value, Layout), DecodeError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(DecodeError::NotSupport("recursive type".to_string()));
+        }
+        self.depth += 1;
+        let mut layout = Layout::UNIT;
+        let value = seed.deserialize(Tracer::new(self, &mut layout));
+        self.depth -= 1;
+        Ok((value?, layout))
+    }
+}
+
+/// A deserializer that writes the layout of the value it deserializes.
+struct Tracer<'a> {
+    registry: &'a mut Registry,
+    layout: &'a mut Layout,
+    /// The encoding of `deserialize_byte_buf`
+    bytes: BytesMode,
+}
+
+impl<'a> Tracer<'a> {
+    fn new(registry: &'a mut Registry, layout: &'a mut Layout) -> Self {
+        Self {
+            registry,
+            layout,
+            bytes: BytesMode::default(),
+        }
+    }
+
+    fn felt(self, name: &'static str) {
+        *self.layout = Layout::Felt(name);
+    }
+}
+
+macro_rules! trace_unsigned {
+    ($method: ident, $visit: ident, $ty: ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+            self.felt(stringify!($ty));
+            visitor.$visit(0)
+        }
+    };
+}
+
+macro_rules! trace_unsupported {
+    ($method: ident, $ty: ty) => {
+        fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, DecodeError> {
+            Err(DecodeError::NotSupport(stringify!($ty).to_string()))
+        }
+    };
+}
+
+impl<'de> Deserializer<'de> for Tracer<'_> {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        Err(DecodeError::NotSupport(
+            (&visitor as &dyn Expected).to_string(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.felt("bool");
+        visitor.visit_bool(false)
+    }
+
+    trace_unsupported!(deserialize_i8, i8);
+    trace_unsupported!(deserialize_i16, i16);
+    trace_unsupported!(deserialize_i32, i32);
+    trace_unsupported!(deserialize_i64, i64);
+    trace_unsupported!(deserialize_i128, i128);
+    trace_unsupported!(deserialize_f32, f32);
+    trace_unsupported!(deserialize_f64, f64);
+    trace_unsigned!(deserialize_u8, visit_u8, u8);
+    trace_unsigned!(deserialize_u16, visit_u16, u16);
+    trace_unsigned!(deserialize_u32, visit_u32, u32);
+    trace_unsigned!(deserialize_u64, visit_u64, u64);
+    trace_unsigned!(deserialize_u128, visit_u128, u128);
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.felt("char");
+        visitor.visit_char('\0')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        // strings are the hex of a single element
+        self.felt("felt252");
+        visitor.visit_str("0")
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        *self.layout = match self.bytes {
+            BytesMode::Elements => Layout::Seq(Box::new(Layout::Felt("u8"))),
+            BytesMode::Bytes31 => Layout::Bytes31,
+            BytesMode::Bytes16 => Layout::Bytes16,
+        };
+        visitor.visit_byte_buf(vec![])
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        let (value, some) = self.registry.trace(OptionSeed(visitor))?;
+        *self.layout = Layout::Enum {
+            name: "Option",
+            variants: vec![("Some", some), ("None", Layout::UNIT)],
+        };
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        *self.layout = Layout::UNIT;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        *self.layout = Layout::Struct {
+            name,
+            fields: vec![],
+        };
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        if name == FELTS_NAME {
+            *self.layout = Layout::Remaining;
+            return visitor.visit_seq(Elements {
+                registry: self.registry,
+                len: 0,
+                layouts: vec![],
+            });
+        }
+        if name == NATIVE_NAME {
+            // the native decoding reads the elements of its smallest value from zeros
+            let (value, items) = self.registry.elements(usize::MAX, visitor)?;
+            *self.layout = Layout::Native {
+                min_len: items.len(),
+            };
+            return Ok(value);
+        }
+        if name == SPAN_NAME {
+            let Tracer {
+                registry, layout, ..
+            } = self;
+            let value = visitor.visit_newtype_struct(Tracer::new(registry, &mut *layout))?;
+            *layout = match std::mem::replace(layout, Layout::UNIT) {
+                Layout::Seq(element) => Layout::Span(element),
+                other => other,
+            };
+            return Ok(value);
+        }
+        match BytesMode::from_newtype_name(name) {
+            Some(bytes) => visitor.visit_newtype_struct(Tracer { bytes, ..self }),
+            None => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        let mut access = SeqElement {
+            registry: self.registry,
+            element: None,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        *self.layout = Layout::Seq(Box::new(access.element.unwrap_or(Layout::UNIT)));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (value, items) = self.registry.elements(len, visitor)?;
+        *self.layout = Layout::Tuple(items);
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        let mut access = MapEntry {
+            registry: self.registry,
+            key: None,
+            value: None,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        *self.layout = Layout::Map {
+            key: Box::new(access.key.unwrap_or(Layout::UNIT)),
+            value: Box::new(access.value.unwrap_or(Layout::UNIT)),
+        };
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (value, items) = self.registry.elements(fields.len(), visitor)?;
+        *self.layout = Layout::Struct {
+            name,
+            fields: fields.iter().copied().zip(items).collect(),
+        };
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let index = self.registry.pick_variant(name, variants)?;
+        self.registry.in_progress.push((name, index));
+        let mut access = Variant {
+            registry: &mut *self.registry,
+            index,
+            name: variants[index],
+            layout: None,
+        };
+        let value = visitor.visit_enum(&mut access);
+        let layout = access.layout.take();
+        self.registry.in_progress.pop();
+        let value = value?;
+        let traced = &mut self.registry.enums.get_mut(name).expect("picked").1[index];
+        if traced.is_none() {
+            *traced = layout;
+        }
+        // the variants are filled once they are all traced
+        *self.layout = Layout::Enum {
+            name,
+            variants: vec![],
+        };
+        Ok(value)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, DecodeError> {
+        Err(DecodeError::IgnoredAnyNotSupported)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Deserialize the `Some` of an option
+struct OptionSeed<V>(V);
+
+impl<'de, V: Visitor<'de>> DeserializeSeed<'de> for OptionSeed<V> {
+    type Value = V::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.0.visit_some(deserializer)
+    }
+}
+
+/// The fields of a struct or the elements of a tuple
+struct Elements<'a> {
+    registry: &'a mut Registry,
+    len: usize,
+    layouts: Vec<Layout>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DecodeError> {
+        if self.layouts.len() == self.len {
+            return Ok(None);
+        }
+        let (value, layout) = self.registry.trace(seed)?;
+        self.layouts.push(layout);
+        Ok(Some(value))
+    }
+}
+
+/// A sequence whose element is traced, then which is empty so the value is valid whatever its
+/// length has to be.
+struct SeqElement<'a> {
+    registry: &'a mut Registry,
+    element: Option<Layout>,
+}
+
+impl<'de> SeqAccess<'de> for SeqElement<'_> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DecodeError> {
+        if self.element.is_none() {
+            let (_, layout) = self.registry.trace(seed)?;
+            self.element = Some(layout);
+        }
+        Ok(None)
+    }
+}
+
+/// A map with a single entry
+struct MapEntry<'a> {
+    registry: &'a mut Registry,
+    key: Option<Layout>,
+    value: Option<Layout>,
+}
+
+impl<'de> MapAccess<'de> for MapEntry<'_> {
+    type Error = DecodeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DecodeError> {
+        if self.key.is_some() {
+            return Ok(None);
+        }
+        let (key, layout) = self.registry.trace(seed)?;
+        self.key = Some(layout);
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (value, layout) = self.registry.trace(seed)?;
+        self.value = Some(layout);
+        Ok(value)
+    }
+}
+
+/// The variant picked by [Registry::pick_variant]
+struct Variant<'a> {
+    registry: &'a mut Registry,
+    index: usize,
+    name: &'static str,
+    layout: Option<Layout>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for &mut Variant<'a> {
+    type Error = DecodeError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self), DecodeError> {
+        let index: de::value::U32Deserializer<DecodeError> =
+            (self.index as u32).into_deserializer();
+        Ok((seed.deserialize(index)?, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for &mut Variant<'a> {
+    type Error = DecodeError;
+
+    fn unit_variant(self) -> Result<(), DecodeError> {
+        self.layout = Some(Layout::UNIT);
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DecodeError> {
+        let (value, layout) = self.registry.trace(seed)?;
+        self.layout = Some(layout);
+        Ok(value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (value, items) = self.registry.elements(len, visitor)?;
+        self.layout = Some(Layout::Tuple(items));
+        Ok(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (value, items) = self.registry.elements(fields.len(), visitor)?;
+        self.layout = Some(Layout::Struct {
+            name: self.name,
+            fields: fields.iter().copied().zip(items).collect(),
+        });
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::array::{Felt, Felts, FixedArray, Span};
+    use crate::proto::{
+        CommitBlockInfo, Deposit, ExecuteBlockInfo, OnchainOperationData, StoredBlockInfo,
+    };
+    use crate::serde::Native;
+    use crate::{to_field_elements, U256};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_primitive_layouts() {
+        assert_eq!(u8::felt_layout().unwrap(), Layout::Felt("u8"));
+        assert_eq!(usize::felt_layout().unwrap(), Layout::Felt("u64"));
+        assert_eq!(String::felt_layout().unwrap(), Layout::Felt("felt252"));
+        assert_eq!(Felt::felt_layout().unwrap(), Layout::Felt("felt252"));
+        assert!(i64::felt_layout().is_err());
+
+        let u256 = U256::felt_layout().unwrap();
+        assert_eq!(u256.to_string(), "U256 { low: u128, high: u128 }");
+        assert_eq!(u256.fixed_len(), Some(2));
+
+        let array = <[U256; 3]>::felt_layout().unwrap();
+        assert_eq!(array.fixed_len(), Some(6));
+        let array = FixedArray::<u8, 40>::felt_layout().unwrap();
+        assert_eq!(array.fixed_len(), Some(40));
+
+        let vec = Vec::<U256>::felt_layout().unwrap();
+        assert_eq!(vec.to_string(), "Array<U256 { low: u128, high: u128 }>");
+        assert_eq!((vec.fixed_len(), vec.min_len()), (None, 1));
+
+        let option = Option::<U256>::felt_layout().unwrap();
+        assert_eq!(
+            option.to_string(),
+            "Option { Some: U256 { low: u128, high: u128 }, None: () }"
+        );
+        assert_eq!((option.fixed_len(), option.min_len()), (None, 1));
+        // variants of the same size
+        let option = Option::<()>::felt_layout().unwrap();
+        assert_eq!(option.fixed_len(), Some(1));
+
+        let span = Span::<Vec<u8>>::felt_layout().unwrap();
+        assert_eq!(span.to_string(), "Span<Array<u8>>");
+
+        let map = HashMap::<u8, Vec<u16>>::felt_layout().unwrap();
+        assert_eq!(map.to_string(), "Map<u8, Array<u16>>");
+        assert_eq!(Felts::felt_layout().unwrap().min_len(), 0);
+
+        let native = Native::<Vec<u8>>::felt_layout().unwrap();
+        assert_eq!(native, Layout::Native { min_len: 1 });
+        assert_eq!((native.fixed_len(), native.min_len()), (None, 1));
+    }
+
+    #[test]
+    fn test_proto_layouts() {
+        let layout = StoredBlockInfo::felt_layout().unwrap();
+        assert_eq!(layout.fixed_len(), Some(11));
+        let encoded = to_field_elements(StoredBlockInfo::default()).unwrap();
+        assert_eq!(layout.fixed_len(), Some(encoded.len()));
+
+        let layout = CommitBlockInfo::felt_layout().unwrap();
+        assert!(!layout.is_fixed_size());
+        let encoded = to_field_elements(CommitBlockInfo::default()).unwrap();
+        assert_eq!(layout.min_len(), encoded.len());
+        let Layout::Struct { fields, .. } = &layout else {
+            panic!("{layout}")
+        };
+        assert_eq!(
+            fields[1].1.to_string(),
+            "Bytes { size: u64, data: Array<u128> }"
+        );
+        assert_eq!(
+            OnchainOperationData::felt_layout().unwrap().min_len(),
+            to_field_elements(OnchainOperationData::default())
+                .unwrap()
+                .len()
+        );
+
+        let layout = ExecuteBlockInfo::felt_layout().unwrap();
+        assert_eq!(layout.min_len(), 12);
+        assert_eq!(Deposit::felt_layout().unwrap().fixed_len(), Some(7));
+    }
+
+    #[test]
+    fn test_enum_layouts() {
+        #[allow(dead_code)]
+        #[derive(Deserialize)]
+        enum Op {
+            Noop,
+            Deposit(u8, U256),
+            Transfer { to: Option<u32>, amount: u128 },
+        }
+        let layout = Op::felt_layout().unwrap();
+        assert_eq!(
+            layout.to_string(),
+            "Op { Noop: (), Deposit: (u8, U256 { low: u128, high: u128 }), \
+             Transfer: Transfer { to: Option { Some: u32, None: () }, amount: u128 } }"
+        );
+        assert_eq!((layout.fixed_len(), layout.min_len()), (None, 1));
+        let result = Result::<u128, u64>::felt_layout().unwrap();
+        assert_eq!(result.fixed_len(), Some(2));
+
+        #[allow(dead_code)]
+        #[derive(Deserialize)]
+        enum Expr {
+            Value(u64),
+            Add(Box<Expr>, Box<Expr>),
+        }
+        assert!(Expr::felt_layout().is_err());
+        #[allow(dead_code)]
+        #[derive(Deserialize)]
+        struct Tree {
+            children: Vec<Tree>,
+        }
+        assert!(Tree::felt_layout().is_err());
+    }
+}
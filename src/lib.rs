@@ -1,15 +1,30 @@
 mod der;
 mod num;
 mod ser;
-mod serde;
 
+pub mod abi;
+pub mod array;
+pub mod calldata;
 pub mod client;
-pub(crate) mod decoder;
-pub(crate) mod encoder;
+pub mod compact;
+pub mod config;
+pub mod decoder;
+pub mod encoder;
 pub mod error;
+pub mod event;
+pub mod explain;
+pub mod fixture;
+pub mod hash;
+pub mod layout;
+pub mod packed;
 pub mod proto;
+pub mod pubdata;
+pub mod serde;
+pub mod sorted_set;
+pub mod versioned;
 
-use crate::decoder::DecoderImpl;
+use crate::config::Config;
+use crate::decoder::{Decoder, DecoderImpl};
 use crate::der::de_owned::SerdeDecoder;
 use crate::der::reader::SliceReader;
 use crate::encoder::EncoderImpl;
@@ -17,40 +32,67 @@ use crate::error::{DecodeError, EncodeError};
 use crate::ser::SerdeEncoder;
 use ::serde::de::DeserializeOwned;
 use ::serde::Serialize;
-pub use num::U256;
+pub use calldata::{from_hex_strings, to_hex_strings, Calldata};
+pub use explain::explain_field_elements;
+pub use num::{Fixed, Rounding, U252, U256, U384, U512, U96};
 use starknet::core::types::FieldElement;
 
 pub fn to_field_elements<T>(t: T) -> Result<Vec<FieldElement>, EncodeError>
 where
     T: Serialize,
 {
-    let mut encoder = EncoderImpl {
-        filed_elements: vec![],
-    };
-    let serializer = SerdeEncoder { enc: &mut encoder };
-    t.serialize(serializer)?;
+    to_field_elements_with_config(t, Config::default())
+}
+
+/// Encode `t` with the options of `config`.
+pub fn to_field_elements_with_config<T>(
+    t: T,
+    config: Config,
+) -> Result<Vec<FieldElement>, EncodeError>
+where
+    T: Serialize,
+{
+    let mut encoder = EncoderImpl::new(config);
+    t.serialize(SerdeEncoder::new(&mut encoder))?;
     Ok(encoder.filed_elements)
 }
 
 /// Attempt to decode a given type `D` from the given slice. Returns the decoded output.
 pub fn from_slice<T>(slice: &[FieldElement]) -> Result<T, DecodeError>
+where
+    T: DeserializeOwned,
+{
+    from_slice_with_config(slice, Config::default())
+}
+
+/// Decode `T` from `slice` with the options of `config`.
+pub fn from_slice_with_config<T>(slice: &[FieldElement], config: Config) -> Result<T, DecodeError>
 where
     T: DeserializeOwned,
 {
     let reader = SliceReader::new(slice);
-    let mut decoder = DecoderImpl::new(reader);
-    let serde_decoder = SerdeDecoder { de: &mut decoder };
-    let result = T::deserialize(serde_decoder)?;
-    Ok(result)
+    let mut decoder = DecoderImpl::with_config(reader, config);
+    let serde_decoder = SerdeDecoder::new(&mut decoder);
+    T::deserialize(serde_decoder).map_err(|e| e.at_offset(decoder.field_elements_read()))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::{Config, MapOrder};
+    use crate::decoder::{Decode, Decoder, DecoderImpl};
+    use crate::der::reader::SliceReader;
+    use crate::encoder::{Encode, EncoderImpl};
+    use crate::error::{DecodeError, EncodeError};
+    use crate::layout::FeltLayout;
     use crate::num::U256;
-    use crate::{from_slice, to_field_elements};
+    use crate::proto::Deposit;
+    use crate::serde::Compat;
+    use crate::{from_slice, to_field_elements, to_field_elements_with_config};
     use primitive_types::U256 as PrimitiveU256;
     use serde::{Deserialize, Serialize};
     use starknet::core::types::FieldElement;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::marker::PhantomData;
 
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
     struct TestStruct {
@@ -122,4 +164,294 @@ mod tests {
         println!("{is_u8_vec_expect:?}");
         assert_eq!(is_u8_vec, is_u8_vec_expect.unwrap());
     }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Witness {
+        id: u8,
+        data: Vec<u16>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Block {
+        number: u64,
+        witnesses: Vec<Witness>,
+    }
+
+    #[test]
+    fn test_decode_error_context() {
+        let block = Block {
+            number: 1,
+            witnesses: vec![
+                Witness {
+                    id: 1,
+                    data: vec![],
+                },
+                Witness {
+                    id: 2,
+                    data: vec![3, 4],
+                },
+            ],
+        };
+        let mut v = to_field_elements(&block).unwrap();
+        // number, len, [id, len], [id, len, 3, 4]
+        v[7] = FieldElement::from(u32::MAX);
+
+        let err = from_slice::<Block>(&v).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::IntegerOutOfRange { offset: 7, .. }
+        ));
+        assert_eq!(err.offset(), Some(7));
+        assert_eq!(err.path().unwrap().to_string(), "witnesses[1].data[1]");
+        assert_eq!(
+            err.to_string(),
+            "element 0xffffffff at offset 7 is out of range of u16 at `witnesses[1].data[1]`"
+        );
+
+        let err = from_slice::<Block>(&v[..4]).unwrap_err();
+        assert_eq!(err.offset(), Some(4));
+        assert_eq!(err.path().unwrap().to_string(), "witnesses[1].id");
+
+        let err = from_slice::<u8>(&[]).unwrap_err();
+        assert_eq!(err.offset(), Some(0));
+        assert!(err.path().unwrap().is_empty());
+        assert_eq!(
+            err.to_string(),
+            "unexpected end, need more 1 elements (element 0)"
+        );
+        let err = from_slice::<u8>(&[FieldElement::from(256u16)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "element 0x100 at offset 0 is out of range of u8"
+        );
+    }
+
+    #[test]
+    fn test_encode_error_context() {
+        let deposit = Deposit {
+            chain_id: 1,
+            account_id: 2,
+            sub_account_id: 0,
+            token_id: 3,
+            target_token_id: 3,
+            amount: 100,
+            owner: "0X9e290521bb937cebdbd1b5636037f089f7bf34de51f9fc019b07cdb8ed98a1".to_string(),
+        };
+        let err = to_field_elements(&deposit).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "owner");
+        assert_eq!(
+            err.to_string(),
+            "only support Hex string, found `0X9e290521bb937cebdbd1b5636037f089f7bf34de51f9fc019b07cdb8ed98a1` at `owner`"
+        );
+
+        let err = to_field_elements(("0x1", vec!["0x2", &"z".repeat(100)])).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "[1][1]");
+        assert!(
+            matches!(err.root_cause(), EncodeError::InvalidString(v) if *v == format!("{}...", "z".repeat(80)))
+        );
+
+        // custom errors of `Serialize` impls are kept
+        fn fail<S: serde::Serializer>(_: &u8, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("bad value"))
+        }
+        #[derive(Serialize)]
+        struct Custom {
+            #[serde(serialize_with = "fail")]
+            value: u8,
+        }
+        let err = to_field_elements(Custom { value: 1 }).unwrap_err();
+        assert_eq!(err.to_string(), "bad value at `value`");
+        assert!(matches!(err.root_cause(), EncodeError::OtherString(v) if v == "bad value"));
+    }
+
+    /// Encode `value`, check the elements, then decode it back from all the elements.
+    fn round_trip<T>(value: T, elements: &[u64])
+    where
+        T: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let encoded = to_field_elements(&value).unwrap();
+        let expected: Vec<_> = elements.iter().map(|e| FieldElement::from(*e)).collect();
+        assert_eq!(encoded, expected, "{value:?}");
+
+        let mut decoder = DecoderImpl::new(SliceReader::new(&encoded));
+        let decoded = Compat::<T>::decode(&mut decoder).unwrap().0;
+        assert_eq!(decoded, value);
+        assert_eq!(decoder.field_elements_read(), encoded.len(), "{value:?}");
+
+        let layout = T::felt_layout().unwrap();
+        match layout.fixed_len() {
+            Some(len) => assert_eq!(len, encoded.len(), "{layout}"),
+            None => assert!(layout.min_len() <= encoded.len(), "{layout}"),
+        }
+    }
+
+    #[test]
+    fn test_data_model_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Unit;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Newtype(u64);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Pair(u8, u16);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Fields {
+            a: u8,
+            unit: (),
+            marker: PhantomData<u8>,
+            b: Unit,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Empty,
+            Newtype(u8),
+            Tuple(u8, u16),
+            Struct { a: u8 },
+            Nothing(()),
+        }
+
+        round_trip((), &[]);
+        round_trip(Unit, &[]);
+        round_trip(Newtype(7), &[7]);
+        round_trip(Pair(1, 2), &[1, 2]);
+        round_trip((1u8, (), 2u16), &[1, 2]);
+        round_trip(
+            Fields {
+                a: 1,
+                unit: (),
+                marker: PhantomData,
+                b: Unit,
+            },
+            &[1],
+        );
+
+        round_trip(Shape::Empty, &[0]);
+        round_trip(Shape::Newtype(5), &[1, 5]);
+        round_trip(Shape::Tuple(1, 2), &[2, 1, 2]);
+        round_trip(Shape::Struct { a: 3 }, &[3, 3]);
+        round_trip(Shape::Nothing(()), &[4]);
+
+        round_trip(Some(()), &[0]);
+        round_trip(None::<()>, &[1]);
+        round_trip(Some(Some(3u8)), &[0, 0, 3]);
+        round_trip(Some(None::<u8>), &[0, 1]);
+        round_trip(None::<Option<u8>>, &[1]);
+        round_trip(Ok::<(), u8>(()), &[0]);
+        round_trip(Err::<(), u8>(4), &[1, 4]);
+
+        round_trip(vec![(), (), ()], &[3]);
+        round_trip(vec![Some(1u8), None], &[2, 0, 1, 1]);
+        round_trip(
+            BTreeMap::from([(1u8, Some(2u8)), (3, None)]),
+            &[2, 1, 0, 2, 3, 1],
+        );
+        round_trip(BTreeMap::from([(1u8, ())]), &[1, 1]);
+
+        // the native impls agree
+        let mut encoder = EncoderImpl::new(Config::default());
+        ((), Some(()), Ok::<(), u8>(()))
+            .encode(&mut encoder)
+            .unwrap();
+        assert_eq!(
+            encoder.filed_elements,
+            to_field_elements(((), Some(()), Ok::<(), u8>(()))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_map_ordering() {
+        let map: HashMap<u64, u8> = (0..50u8).map(|i| (u64::from(i) * 7 % 50, i)).collect();
+        let sorted: BTreeMap<u64, u8> = map.clone().into_iter().collect();
+        let v = to_field_elements(&map).unwrap();
+        assert_eq!(v, to_field_elements(&sorted).unwrap());
+        assert_eq!(v[..3], [50u8, 0, 0].map(FieldElement::from));
+        assert_eq!(from_slice::<HashMap<u64, u8>>(&v).unwrap(), map);
+        assert_eq!(from_slice::<BTreeMap<u64, u8>>(&v).unwrap(), sorted);
+
+        // the native encoding is the same
+        let mut encoder = EncoderImpl::new(Config::default());
+        map.encode(&mut encoder).unwrap();
+        assert_eq!(encoder.filed_elements, v);
+        let mut decoder = DecoderImpl::new(SliceReader::new(&v));
+        assert_eq!(HashMap::<u64, u8>::decode(&mut decoder).unwrap(), map);
+
+        let set: HashSet<u64> = map.keys().copied().collect();
+        let expected = to_field_elements(sorted.keys().collect::<Vec<_>>()).unwrap();
+        let mut encoder = EncoderImpl::new(Config::default());
+        set.encode(&mut encoder).unwrap();
+        assert_eq!(encoder.filed_elements, expected);
+        let mut decoder = DecoderImpl::new(SliceReader::new(&expected));
+        assert_eq!(BTreeSet::<u64>::decode(&mut decoder).unwrap().len(), 50);
+
+        #[derive(Serialize, Deserialize)]
+        struct Sets {
+            #[serde(with = "crate::sorted_set")]
+            set: HashSet<u64>,
+        }
+        let v = to_field_elements(Sets { set: set.clone() }).unwrap();
+        assert_eq!(v, expected);
+        assert_eq!(from_slice::<Sets>(&v).unwrap().set, set);
+
+        let strict = Config::new().with_map_order(MapOrder::Strict);
+        assert_eq!(
+            to_field_elements_with_config(&sorted, strict).unwrap(),
+            to_field_elements(&sorted).unwrap()
+        );
+        // the iteration order of a `HashMap` doesn't matter
+        assert_eq!(
+            to_field_elements_with_config(&map, strict).unwrap(),
+            to_field_elements(&sorted).unwrap()
+        );
+        // "0x10" < "0x9" but 0x10 > 0x9
+        let unordered = BTreeMap::from([("0x10".to_string(), 1u8), ("0x9".to_string(), 2u8)]);
+        let v = to_field_elements(&unordered).unwrap();
+        assert_eq!(v[..3], [2u8, 9, 2].map(FieldElement::from));
+        assert_eq!(
+            to_field_elements_with_config(&unordered, strict).unwrap(),
+            v
+        );
+
+        let mixed = BTreeMap::from([(vec![1u8], 1u8), (vec![1, 2], 2u8)]);
+        assert_eq!(
+            to_field_elements(&mixed).unwrap(),
+            to_field_elements([2u8, 1, 1, 1, 2, 1, 2, 2]).unwrap()
+        );
+        let err = to_field_elements_with_config(&mixed, strict).unwrap_err();
+        assert!(matches!(err, EncodeError::MixedKeyLengths));
+        let mut encoder = EncoderImpl::new(strict);
+        let err = BTreeMap::from([(None, 1u8), (Some(1u8), 2)])
+            .encode(&mut encoder)
+            .unwrap_err();
+        assert!(matches!(err, EncodeError::MixedKeyLengths));
+
+        // `sorted_set` uses the config of the encoder
+        let sets = Sets {
+            set: HashSet::from([1u64, 2]),
+        };
+        assert!(to_field_elements_with_config(&sets, strict).is_ok());
+        #[derive(Serialize)]
+        struct MixedSets {
+            #[serde(with = "crate::sorted_set")]
+            set: HashSet<Vec<u8>>,
+        }
+        let sets = MixedSets {
+            set: HashSet::from([vec![1u8], vec![1, 2]]),
+        };
+        let err = to_field_elements_with_config(&sets, strict).unwrap_err();
+        assert!(matches!(err.root_cause(), EncodeError::MixedKeyLengths));
+        assert_eq!(
+            serde_json::to_string(&Sets {
+                set: HashSet::from([3u64])
+            })
+            .unwrap(),
+            r#"{"set":[3]}"#
+        );
+
+        let duplicate = BTreeMap::from([("0x1".to_string(), 1u8), ("1".to_string(), 2u8)]);
+        let err = to_field_elements(&duplicate).unwrap_err();
+        assert!(matches!(err, EncodeError::DuplicateMapKey));
+    }
 }
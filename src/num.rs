@@ -1,12 +1,184 @@
+use crate::array::Felt;
+use crate::error::NumError;
 pub use primitive_types::U256 as PrimitiveU256;
-use serde::{Deserialize, Serialize};
+pub use primitive_types::U512 as PrimitiveU512;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::types::FieldElement;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, BitXor, Div, Mul, MulAssign, Not, Rem, Shl, Shr, Sub, SubAssign,
+};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The cairo `u256`, encoded as two `u128` limbs, the low limb first.
+///
+/// With a human readable serde format(e.g. json), it's serialized as a hex string with "0x"
+/// prefix and deserialized from a hex or decimal string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct U256 {
     low: u128,
     high: u128,
 }
 
+/// The layout of [U256] in the non human readable formats
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "U256")]
+struct Limbs {
+    low: u128,
+    high: u128,
+}
+
+impl U256 {
+    pub const ZERO: U256 = U256::new(0, 0);
+    pub const ONE: U256 = U256::new(1, 0);
+    pub const MAX: U256 = U256::new(u128::MAX, u128::MAX);
+
+    pub const fn new(low: u128, high: u128) -> Self {
+        Self { low, high }
+    }
+
+    pub const fn low(&self) -> u128 {
+        self.low
+    }
+
+    pub const fn high(&self) -> u128 {
+        self.high
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let (low, carry) = self.low.overflowing_add(rhs.low);
+        let (high, overflow1) = self.high.overflowing_add(rhs.high);
+        let (high, overflow2) = high.overflowing_add(carry as u128);
+        (Self::new(low, high), overflow1 || overflow2)
+    }
+
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let (low, borrow) = self.low.overflowing_sub(rhs.low);
+        let (high, overflow1) = self.high.overflowing_sub(rhs.high);
+        let (high, overflow2) = high.overflowing_sub(borrow as u128);
+        (Self::new(low, high), overflow1 || overflow2)
+    }
+
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let (num, overflow) = PrimitiveU256::from(self).overflowing_mul(rhs.into());
+        (num.into(), overflow)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_add(rhs) {
+            (num, false) => Some(num),
+            _ => None,
+        }
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_sub(rhs) {
+            (num, false) => Some(num),
+            _ => None,
+        }
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_mul(rhs) {
+            (num, false) => Some(num),
+            _ => None,
+        }
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        PrimitiveU256::from(self)
+            .checked_div(rhs.into())
+            .map(Into::into)
+    }
+
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        PrimitiveU256::from(self)
+            .checked_rem(rhs.into())
+            .map(Into::into)
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        self.overflowing_add(rhs).0
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        self.overflowing_sub(rhs).0
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).unwrap_or(Self::MAX)
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or(Self::ZERO)
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&self.high.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.low.to_be_bytes());
+        bytes
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.to_be_bytes();
+        bytes.reverse();
+        bytes
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut high = [0u8; 16];
+        let mut low = [0u8; 16];
+        high.copy_from_slice(&bytes[..16]);
+        low.copy_from_slice(&bytes[16..]);
+        Self::new(u128::from_be_bytes(low), u128::from_be_bytes(high))
+    }
+
+    pub fn from_le_bytes(mut bytes: [u8; 32]) -> Self {
+        bytes.reverse();
+        Self::from_be_bytes(bytes)
+    }
+
+    /// Parse a hex string with or without "0x" prefix.
+    pub fn from_hex_str(s: &str) -> Result<Self, NumError> {
+        let invalid = || NumError::InvalidString(s.to_string());
+        let hex_str = s.strip_prefix("0x").unwrap_or(s);
+        if hex_str.is_empty() {
+            return Err(invalid());
+        }
+        let digits = hex_str.trim_start_matches('0');
+        if digits.len() > 64 {
+            return Err(if hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+                NumError::Overflow
+            } else {
+                invalid()
+            });
+        }
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(format!("{digits:0>64}"), &mut bytes).map_err(|_| invalid())?;
+        Ok(Self::from_be_bytes(bytes))
+    }
+
+    /// Parse a decimal string.
+    pub fn from_dec_str(s: &str) -> Result<Self, NumError> {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(NumError::InvalidString(s.to_string()));
+        }
+        PrimitiveU256::from_dec_str(s)
+            .map(Into::into)
+            .map_err(|_| NumError::Overflow)
+    }
+}
+
 impl From<PrimitiveU256> for U256 {
     fn from(value: PrimitiveU256) -> Self {
         let low = value.low_u128();
@@ -24,6 +196,605 @@ impl From<U256> for PrimitiveU256 {
     }
 }
 
+macro_rules! impl_from_unsigned {
+    ($ty: ty) => {
+        impl From<$ty> for U256 {
+            fn from(value: $ty) -> Self {
+                Self::new(value as u128, 0)
+            }
+        }
+    };
+}
+
+impl_from_unsigned!(u8);
+impl_from_unsigned!(u16);
+impl_from_unsigned!(u32);
+impl_from_unsigned!(u64);
+impl_from_unsigned!(u128);
+impl_from_unsigned!(usize);
+
+impl From<FieldElement> for U256 {
+    fn from(value: FieldElement) -> Self {
+        Self::from_be_bytes(value.to_bytes_be())
+    }
+}
+
+impl TryFrom<U256> for FieldElement {
+    type Error = NumError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        FieldElement::from_bytes_be(&value.to_be_bytes()).map_err(|_| NumError::Overflow)
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.high, self.low).cmp(&(other.high, other.low))
+    }
+}
+
+impl Add for U256 {
+    type Output = U256;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("U256 addition overflow")
+    }
+}
+
+impl AddAssign for U256 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for U256 {
+    type Output = U256;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("U256 subtraction overflow")
+    }
+}
+
+impl SubAssign for U256 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for U256 {
+    type Output = U256;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs).expect("U256 multiplication overflow")
+    }
+}
+
+impl MulAssign for U256 {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div for U256 {
+    type Output = U256;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs).expect("U256 division by zero")
+    }
+}
+
+impl Rem for U256 {
+    type Output = U256;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.checked_rem(rhs).expect("U256 division by zero")
+    }
+}
+
+macro_rules! impl_bit_op {
+    ($trait: ident, $method: ident, $op: tt) => {
+        impl $trait for U256 {
+            type Output = U256;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                Self::new(self.low $op rhs.low, self.high $op rhs.high)
+            }
+        }
+    };
+}
+
+impl_bit_op!(BitAnd, bitand, &);
+impl_bit_op!(BitOr, bitor, |);
+impl_bit_op!(BitXor, bitxor, ^);
+
+impl Not for U256 {
+    type Output = U256;
+
+    fn not(self) -> Self::Output {
+        Self::new(!self.low, !self.high)
+    }
+}
+
+impl Shl<u32> for U256 {
+    type Output = U256;
+
+    fn shl(self, rhs: u32) -> Self::Output {
+        (PrimitiveU256::from(self) << rhs).into()
+    }
+}
+
+impl Shr<u32> for U256 {
+    type Output = U256;
+
+    fn shr(self, rhs: u32) -> Self::Output {
+        (PrimitiveU256::from(self) >> rhs).into()
+    }
+}
+
+/// Parse a decimal string, or a hex string with "0x" prefix
+impl FromStr for U256 {
+    type Err = NumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x") {
+            Self::from_hex_str(s)
+        } else {
+            Self::from_dec_str(s)
+        }
+    }
+}
+
+/// Decimal string
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&PrimitiveU256::from(*self), f)
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&PrimitiveU256::from(*self), f)
+    }
+}
+
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&format_args!("{self:#x}"))
+        } else {
+            Limbs {
+                low: self.low,
+                high: self.high,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(de::Error::custom)
+        } else {
+            let limbs = Limbs::deserialize(deserializer)?;
+            Ok(Self::new(limbs.low, limbs.high))
+        }
+    }
+}
+
+/// The cairo `u512`, encoded as four `u128` limbs, the least significant limb first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct U512 {
+    pub limb0: u128,
+    pub limb1: u128,
+    pub limb2: u128,
+    pub limb3: u128,
+}
+
+impl U512 {
+    pub fn from_be_bytes(bytes: [u8; 64]) -> Self {
+        let limb = |i: usize| {
+            let mut limb = [0u8; 16];
+            limb.copy_from_slice(&bytes[64 - 16 * (i + 1)..64 - 16 * i]);
+            u128::from_be_bytes(limb)
+        };
+        Self {
+            limb0: limb(0),
+            limb1: limb(1),
+            limb2: limb(2),
+            limb3: limb(3),
+        }
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        for (i, limb) in [self.limb3, self.limb2, self.limb1, self.limb0]
+            .iter()
+            .enumerate()
+        {
+            bytes[16 * i..16 * (i + 1)].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+impl From<PrimitiveU512> for U512 {
+    fn from(value: PrimitiveU512) -> Self {
+        let mut bytes = [0u8; 64];
+        value.to_big_endian(&mut bytes);
+        Self::from_be_bytes(bytes)
+    }
+}
+
+impl From<U512> for PrimitiveU512 {
+    fn from(value: U512) -> Self {
+        PrimitiveU512::from_big_endian(&value.to_be_bytes())
+    }
+}
+
+impl From<U256> for U512 {
+    fn from(value: U256) -> Self {
+        Self {
+            limb0: value.low,
+            limb1: value.high,
+            limb2: 0,
+            limb3: 0,
+        }
+    }
+}
+
+impl From<PrimitiveU256> for U512 {
+    fn from(value: PrimitiveU256) -> Self {
+        U256::from(value).into()
+    }
+}
+
+impl TryFrom<U512> for U256 {
+    type Error = NumError;
+
+    fn try_from(value: U512) -> Result<Self, Self::Error> {
+        if value.limb2 != 0 || value.limb3 != 0 {
+            return Err(NumError::Overflow);
+        }
+        Ok(U256::new(value.limb0, value.limb1))
+    }
+}
+
+/// The cairo `u96`, the limb of [U384], encoded as one element.
+///
+/// It must be less than 2^96, which is checked by [U96::new] and when decoding.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "u128", into = "u128")]
+pub struct U96(u128);
+
+impl U96 {
+    pub const BITS: u32 = 96;
+    pub const MAX: Self = Self((1 << Self::BITS) - 1);
+
+    pub fn new(value: u128) -> Result<Self, NumError> {
+        if value >> Self::BITS != 0 {
+            return Err(NumError::Overflow);
+        }
+        Ok(Self(value))
+    }
+
+    pub fn get(&self) -> u128 {
+        self.0
+    }
+}
+
+impl TryFrom<u128> for U96 {
+    type Error = NumError;
+
+    fn try_from(value: u128) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<U96> for u128 {
+    fn from(value: U96) -> Self {
+        value.0
+    }
+}
+
+impl From<u64> for U96 {
+    fn from(value: u64) -> Self {
+        Self(value.into())
+    }
+}
+
+/// A 384 bits number encoded as four [U96] limbs, the least significant limb first, which is
+/// the layout of the 384 bits field elements of the verifier.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct U384 {
+    limb0: U96,
+    limb1: U96,
+    limb2: U96,
+    limb3: U96,
+}
+
+impl U384 {
+    const LIMB_BYTES: usize = 12;
+
+    /// Construct from the `u96` limbs, the least significant limb first.
+    pub fn from_limbs(limbs: [u128; 4]) -> Result<Self, NumError> {
+        Ok(Self::from_u96_limbs([
+            U96::new(limbs[0])?,
+            U96::new(limbs[1])?,
+            U96::new(limbs[2])?,
+            U96::new(limbs[3])?,
+        ]))
+    }
+
+    pub fn from_u96_limbs(limbs: [U96; 4]) -> Self {
+        Self {
+            limb0: limbs[0],
+            limb1: limbs[1],
+            limb2: limbs[2],
+            limb3: limbs[3],
+        }
+    }
+
+    pub fn limbs(&self) -> [u128; 4] {
+        self.u96_limbs().map(u128::from)
+    }
+
+    pub fn u96_limbs(&self) -> [U96; 4] {
+        [self.limb0, self.limb1, self.limb2, self.limb3]
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 48]) -> Self {
+        let mut limbs = [0u128; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let end = 48 - Self::LIMB_BYTES * i;
+            let mut data = [0u8; 16];
+            data[16 - Self::LIMB_BYTES..].copy_from_slice(&bytes[end - Self::LIMB_BYTES..end]);
+            *limb = u128::from_be_bytes(data);
+        }
+        Self::from_limbs(limbs).expect("limbs of 12 bytes")
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 48] {
+        let mut bytes = [0u8; 48];
+        for (i, limb) in self.limbs().iter().enumerate() {
+            let end = 48 - Self::LIMB_BYTES * i;
+            bytes[end - Self::LIMB_BYTES..end]
+                .copy_from_slice(&limb.to_be_bytes()[16 - Self::LIMB_BYTES..]);
+        }
+        bytes
+    }
+}
+
+impl TryFrom<PrimitiveU512> for U384 {
+    type Error = NumError;
+
+    fn try_from(value: PrimitiveU512) -> Result<Self, Self::Error> {
+        if value.bits() > 384 {
+            return Err(NumError::Overflow);
+        }
+        let mut bytes = [0u8; 64];
+        value.to_big_endian(&mut bytes);
+        let mut data = [0u8; 48];
+        data.copy_from_slice(&bytes[16..]);
+        Ok(Self::from_be_bytes(data))
+    }
+}
+
+impl From<U384> for PrimitiveU512 {
+    fn from(value: U384) -> Self {
+        PrimitiveU512::from_big_endian(&value.to_be_bytes())
+    }
+}
+
+impl From<U256> for U384 {
+    fn from(value: U256) -> Self {
+        let mut bytes = [0u8; 48];
+        bytes[16..].copy_from_slice(&value.to_be_bytes());
+        Self::from_be_bytes(bytes)
+    }
+}
+
+impl From<PrimitiveU256> for U384 {
+    fn from(value: PrimitiveU256) -> Self {
+        U256::from(value).into()
+    }
+}
+
+/// A number less than the field modulus, encoded as one `felt252` element.
+///
+/// The range is checked by the constructors and when it's deserialized from a string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U252(U256);
+
+impl U252 {
+    pub fn new(value: U256) -> Result<Self, NumError> {
+        FieldElement::try_from(value)?;
+        Ok(Self(value))
+    }
+
+    pub fn get(&self) -> U256 {
+        self.0
+    }
+}
+
+impl TryFrom<U256> for U252 {
+    type Error = NumError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<PrimitiveU256> for U252 {
+    type Error = NumError;
+
+    fn try_from(value: PrimitiveU256) -> Result<Self, Self::Error> {
+        Self::new(value.into())
+    }
+}
+
+impl From<U252> for U256 {
+    fn from(value: U252) -> Self {
+        value.0
+    }
+}
+
+impl From<FieldElement> for U252 {
+    fn from(value: FieldElement) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<U252> for FieldElement {
+    fn from(value: U252) -> Self {
+        FieldElement::from_bytes_be(&value.0.to_be_bytes()).expect("checked by the constructors")
+    }
+}
+
+impl Serialize for U252 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Felt(FieldElement::from(*self)).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for U252 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Felt::deserialize(deserializer)?.0.into())
+    }
+}
+
+/// How a decimal is rounded to the precision of [Fixed]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Toward zero
+    Down,
+    /// Away from zero
+    Up,
+    /// To the nearest, ties away from zero
+    HalfUp,
+    /// To the nearest, ties to the even neighbour
+    HalfEven,
+}
+
+/// A non negative decimal with `DECIMALS` fractional digits, encoded as the integer
+/// `value * 10^DECIMALS` in one element, e.g. the prices of the oracle feed.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Fixed<const DECIMALS: u32>(u128);
+
+impl<const DECIMALS: u32> Fixed<DECIMALS> {
+    /// `10^DECIMALS`, `DECIMALS` can't be more than 38
+    pub const SCALE: u128 = 10u128.pow(DECIMALS);
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_scaled(scaled: u128) -> Self {
+        Self(scaled)
+    }
+
+    /// The encoded integer `value * 10^DECIMALS`
+    pub const fn scaled(&self) -> u128 {
+        self.0
+    }
+
+    /// Round the shortest decimal representation of `value`, which must be finite and not
+    /// negative.
+    pub fn from_f64(value: f64, rounding: Rounding) -> Result<Self, NumError> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(NumError::InvalidFloat(value.to_string()));
+        }
+        Self::from_str_rounded(&value.abs().to_string(), rounding)
+    }
+
+    /// Like [Self::from_f64], `value` is not widened so its shortest representation is kept.
+    pub fn from_f32(value: f32, rounding: Rounding) -> Result<Self, NumError> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(NumError::InvalidFloat(value.to_string()));
+        }
+        Self::from_str_rounded(&value.abs().to_string(), rounding)
+    }
+
+    /// The nearest float of the decimal
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().expect("decimal string")
+    }
+
+    /// Parse a decimal string like `123.45`, rounded to `DECIMALS` fractional digits.
+    pub fn from_str_rounded(s: &str, rounding: Rounding) -> Result<Self, NumError> {
+        let (scaled, rest) = Self::parse(s)?;
+        let nonzero = |digits: &[u8]| digits.iter().any(|d| *d != b'0');
+        let round_up = match (rounding, rest.first()) {
+            (_, None) | (Rounding::Down, _) => false,
+            (Rounding::Up, _) => nonzero(rest),
+            (Rounding::HalfUp, Some(d)) => *d >= b'5',
+            (Rounding::HalfEven, Some(b'5')) => nonzero(&rest[1..]) || scaled % 2 == 1,
+            (Rounding::HalfEven, Some(d)) => *d > b'5',
+        };
+        if round_up {
+            scaled.checked_add(1).map(Self).ok_or(NumError::Overflow)
+        } else {
+            Ok(Self(scaled))
+        }
+    }
+
+    /// Parse the decimal truncated to `DECIMALS` fractional digits, and the truncated digits.
+    fn parse(s: &str) -> Result<(u128, &[u8]), NumError> {
+        let invalid = || NumError::InvalidString(s.to_string());
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |digits: &str| digits.bytes().all(|d| d.is_ascii_digit());
+        if int.is_empty() || !is_digits(int) || !is_digits(frac) {
+            return Err(invalid());
+        }
+        let decimals = DECIMALS as usize;
+        let (kept, rest) = frac.as_bytes().split_at(frac.len().min(decimals));
+        let mut scaled = 0u128;
+        for d in int.bytes().chain(kept.iter().copied()) {
+            scaled = scaled
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(u128::from(d - b'0')))
+                .ok_or(NumError::Overflow)?;
+        }
+        let padding = (decimals - kept.len()) as u32;
+        let scaled = scaled
+            .checked_mul(10u128.pow(padding))
+            .ok_or(NumError::Overflow)?;
+        Ok((scaled, rest))
+    }
+}
+
+/// Parse a decimal string, which can't have more than `DECIMALS` fractional digits.
+impl<const DECIMALS: u32> FromStr for Fixed<DECIMALS> {
+    type Err = NumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::parse(s)? {
+            (scaled, []) => Ok(Self(scaled)),
+            _ => Err(NumError::InvalidString(s.to_string())),
+        }
+    }
+}
+
+impl<const DECIMALS: u32> fmt::Display for Fixed<DECIMALS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let int = self.0 / Self::SCALE;
+        if DECIMALS == 0 {
+            return write!(f, "{int}");
+        }
+        let frac = self.0 % Self::SCALE;
+        write!(f, "{int}.{frac:0width$}", width = DECIMALS as usize)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -39,4 +810,245 @@ mod test {
         let num2: PrimitiveU256 = u.into();
         assert_eq!(num, num2);
     }
+
+    #[test]
+    fn test_u256_arithmetic() {
+        let a = U256::new(u128::MAX, 1);
+        let b = U256::from(1u8);
+        assert_eq!(a + b, U256::new(0, 2));
+        assert_eq!(U256::new(0, 2) - b, a);
+        assert_eq!(U256::MAX.checked_add(b), None);
+        assert_eq!(U256::MAX.wrapping_add(b), U256::ZERO);
+        assert_eq!(U256::ZERO.checked_sub(b), None);
+        assert_eq!(U256::ZERO.wrapping_sub(b), U256::MAX);
+        assert_eq!(U256::ZERO.saturating_sub(b), U256::ZERO);
+        assert_eq!(
+            U256::from(u128::MAX) * U256::from(2u8),
+            U256::new(u128::MAX - 1, 1)
+        );
+        assert_eq!(U256::new(0, 1).checked_mul(U256::new(0, 1)), None);
+        assert_eq!(U256::new(0, 4) / U256::from(2u8), U256::new(0, 2));
+        assert_eq!(U256::from(7u8) % U256::from(4u8), U256::from(3u8));
+        assert_eq!(b.checked_div(U256::ZERO), None);
+
+        assert!(U256::new(0, 1) > U256::new(u128::MAX, 0));
+        assert!(U256::new(1, 1) > U256::new(0, 1));
+        let mut nums = vec![U256::new(0, 1), U256::ONE, U256::ZERO];
+        nums.sort();
+        assert_eq!(nums, vec![U256::ZERO, U256::ONE, U256::new(0, 1)]);
+
+        assert_eq!(b << 128, U256::new(0, 1));
+        assert_eq!(U256::new(0, 1) >> 127, U256::from(2u8));
+        assert_eq!(!U256::ZERO, U256::MAX);
+        assert_eq!(a & U256::new(1, 1), U256::new(1, 1));
+        assert_eq!(a | U256::new(0, 2), U256::new(u128::MAX, 3));
+        assert_eq!(a ^ a, U256::ZERO);
+    }
+
+    #[test]
+    fn test_u256_string() {
+        let n: U256 = "340282366920938463463374607431768211456".parse().unwrap();
+        assert_eq!(n, U256::new(0, 1));
+        assert_eq!(n.to_string(), "340282366920938463463374607431768211456");
+        let n: U256 = "0x100000000000000000000000000000001".parse().unwrap();
+        assert_eq!(n, U256::new(1, 1));
+        assert_eq!(format!("{n:#x}"), "0x100000000000000000000000000000001");
+        assert_eq!(format!("{:x}", U256::from(255u8)), "ff");
+        assert_eq!(U256::MAX.to_string().parse::<U256>().unwrap(), U256::MAX);
+        assert_eq!(
+            format!("{:#x}", U256::MAX).parse::<U256>().unwrap(),
+            U256::MAX
+        );
+
+        assert_eq!(
+            "0x1".repeat(33).parse::<U256>(),
+            Err(NumError::InvalidString("0x1".repeat(33)))
+        );
+        assert_eq!(
+            format!("0x1{}", "0".repeat(64)).parse::<U256>(),
+            Err(NumError::Overflow)
+        );
+        assert_eq!(
+            format!("1{}", U256::MAX).parse::<U256>(),
+            Err(NumError::Overflow)
+        );
+        assert!("".parse::<U256>().is_err());
+        assert!("0x".parse::<U256>().is_err());
+        assert!("12a".parse::<U256>().is_err());
+        assert!("-1".parse::<U256>().is_err());
+    }
+
+    #[test]
+    fn test_u256_bytes_and_field_element() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 1;
+        bytes[31] = 2;
+        let n = U256::from_be_bytes(bytes);
+        assert_eq!(n, U256::new(2, 1 << 120));
+        assert_eq!(n.to_be_bytes(), bytes);
+        assert_eq!(U256::from_le_bytes(n.to_le_bytes()), n);
+        assert_eq!(n.to_le_bytes()[0], 2);
+
+        let f = FieldElement::MAX;
+        let n = U256::from(f);
+        assert_eq!(FieldElement::try_from(n).unwrap(), f);
+        assert_eq!(
+            FieldElement::try_from(n + U256::ONE),
+            Err(NumError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_u256_serde() {
+        let n = U256::new(1, 1);
+        let elements = crate::to_field_elements(n).unwrap();
+        assert_eq!(elements, vec![FieldElement::ONE, FieldElement::ONE]);
+        assert_eq!(crate::from_slice::<U256>(&elements).unwrap(), n);
+    }
+
+    #[test]
+    fn test_u256_json() {
+        let n = U256::new(1, 1);
+        let json = serde_json::to_string(&n).unwrap();
+        assert_eq!(json, "\"0x100000000000000000000000000000001\"");
+        assert_eq!(serde_json::from_str::<U256>(&json).unwrap(), n);
+        assert_eq!(
+            serde_json::from_str::<U256>("\"255\"").unwrap(),
+            U256::from(255u8)
+        );
+    }
+
+    #[test]
+    fn test_u512() {
+        let num = PrimitiveU512::from_big_endian(&(1..=64).collect::<Vec<u8>>());
+        let u: U512 = num.into();
+        assert_eq!(
+            u.limb0,
+            u128::from_be_bytes((49..=64).collect::<Vec<u8>>().try_into().unwrap())
+        );
+        assert_eq!(
+            u.limb3,
+            u128::from_be_bytes((1..=16).collect::<Vec<u8>>().try_into().unwrap())
+        );
+        assert_eq!(PrimitiveU512::from(u), num);
+
+        let elements = crate::to_field_elements(u).unwrap();
+        assert_eq!(elements.len(), 4);
+        assert_eq!(elements[0], FieldElement::from(u.limb0));
+        assert_eq!(crate::from_slice::<U512>(&elements).unwrap(), u);
+
+        let n = U256::new(1, 2);
+        assert_eq!(U256::try_from(U512::from(n)).unwrap(), n);
+        assert_eq!(U256::try_from(u), Err(NumError::Overflow));
+    }
+
+    #[test]
+    fn test_u384() {
+        let num = (PrimitiveU512::one() << 383) + PrimitiveU512::from(5u8);
+        let u = U384::try_from(num).unwrap();
+        assert_eq!(u.limbs(), [5, 0, 0, 1 << 95]);
+        assert_eq!(PrimitiveU512::from(u), num);
+        assert_eq!(
+            U384::try_from(PrimitiveU512::one() << 384),
+            Err(NumError::Overflow)
+        );
+
+        let elements = crate::to_field_elements(u).unwrap();
+        assert_eq!(elements.len(), 4);
+        assert_eq!(crate::from_slice::<U384>(&elements).unwrap(), u);
+
+        let n: U384 = PrimitiveU256::MAX.into();
+        assert_eq!(n.limbs(), [(1 << 96) - 1, (1 << 96) - 1, (1 << 64) - 1, 0]);
+        assert_eq!(U384::from_be_bytes(n.to_be_bytes()), n);
+
+        assert_eq!(
+            U384::from_limbs([1 << 96, 0, 0, 0]),
+            Err(NumError::Overflow)
+        );
+        let invalid = crate::to_field_elements([1u128 << 96, 0, 0, 0]).unwrap();
+        assert!(crate::from_slice::<U384>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_u96() {
+        assert_eq!(U96::new((1 << 96) - 1), Ok(U96::MAX));
+        assert_eq!(U96::new(1 << 96), Err(NumError::Overflow));
+        assert_eq!(U96::from(7u64).get(), 7);
+
+        let elements = crate::to_field_elements(U96::MAX).unwrap();
+        assert_eq!(elements, [FieldElement::from(U96::MAX.get())]);
+        assert_eq!(crate::from_slice::<U96>(&elements).unwrap(), U96::MAX);
+        let invalid = crate::to_field_elements(1u128 << 96).unwrap();
+        assert!(crate::from_slice::<U96>(&invalid).is_err());
+
+        let u = U384::from_u96_limbs([U96::MAX, U96::from(1u64), U96::default(), U96::MAX]);
+        assert_eq!(U384::from_limbs(u.limbs()).unwrap(), u);
+        assert_eq!(u.u96_limbs()[1].get(), 1);
+    }
+
+    #[test]
+    fn test_u252() {
+        let max = U256::from(FieldElement::MAX);
+        let u = U252::new(max).unwrap();
+        assert_eq!(FieldElement::from(u), FieldElement::MAX);
+        assert_eq!(U252::new(max + U256::from(1u8)), Err(NumError::Overflow));
+        assert_eq!(U252::try_from(PrimitiveU256::MAX), Err(NumError::Overflow));
+
+        let elements = crate::to_field_elements(u).unwrap();
+        assert_eq!(elements, [FieldElement::MAX]);
+        assert_eq!(crate::from_slice::<U252>(&elements).unwrap(), u);
+        assert_eq!(U256::from(U252::from(FieldElement::TWO)), U256::from(2u8));
+
+        let json = serde_json::to_string(&u).unwrap();
+        assert_eq!(serde_json::from_str::<U252>(&json).unwrap(), u);
+        let modulus = "\"0x800000000000011000000000000000000000000000000000000000000000001\"";
+        assert!(serde_json::from_str::<U252>(modulus).is_err());
+    }
+
+    #[test]
+    fn test_fixed() {
+        type Price = Fixed<2>;
+        let round = |v: f64, rounding| Price::from_f64(v, rounding).unwrap().to_string();
+        assert_eq!(round(1234.5678, Rounding::Down), "1234.56");
+        assert_eq!(round(1234.5678, Rounding::HalfUp), "1234.57");
+        assert_eq!(round(1.001, Rounding::Up), "1.01");
+        assert_eq!(round(1.001, Rounding::HalfUp), "1.00");
+        assert_eq!(round(0.125, Rounding::HalfEven), "0.12");
+        assert_eq!(round(0.135, Rounding::HalfEven), "0.14");
+        assert_eq!(round(0.1251, Rounding::HalfEven), "0.13");
+        assert_eq!(round(0.125, Rounding::HalfUp), "0.13");
+        assert_eq!(round(3.0, Rounding::Up), "3.00");
+        assert_eq!(round(-0.0, Rounding::Up), "0.00");
+        // the shortest representation of the f32 is kept
+        assert_eq!(Price::from_f32(0.29, Rounding::Up).unwrap().scaled(), 29);
+
+        for v in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                Price::from_f64(v, Rounding::Down),
+                Err(NumError::InvalidFloat(_))
+            ));
+        }
+        assert_eq!(
+            Price::from_f64(1e40, Rounding::Down),
+            Err(NumError::Overflow)
+        );
+
+        let price: Price = "12.3".parse().unwrap();
+        assert_eq!(price.scaled(), 1230);
+        assert_eq!(price.to_f64(), 12.3);
+        assert!("12.345".parse::<Price>().is_err());
+        assert!(".5".parse::<Price>().is_err());
+        assert!("1e3".parse::<Price>().is_err());
+        assert_eq!("7".parse::<Fixed<0>>().unwrap().to_string(), "7");
+        assert_eq!(
+            Fixed::<18>::from_str_rounded("0.1234567890123456789", Rounding::HalfUp)
+                .unwrap()
+                .scaled(),
+            123456789012345679
+        );
+
+        let encoded = crate::to_field_elements(price).unwrap();
+        assert_eq!(encoded, vec![FieldElement::from(1230u32)]);
+        assert_eq!(crate::from_slice::<Price>(&encoded).unwrap(), price);
+    }
 }
@@ -0,0 +1,352 @@
+//! Serde adapters that pack bytes into words instead of one element per byte, for use with
+//! `#[serde(with = "stark_util::packed::bytes31")]` on `Vec<u8>` fields.
+//!
+//! The words are big-endian. [bytes31] has the layout of a cairo `ByteArray`, whose last partial
+//! word is a number with its own length, while the last word of [bytes16] is padded with zeros
+//! at the end like [crate::proto::Bytes].
+//!
+//! [bytes31] has no total length header: the length is the number of full words times 31 plus
+//! the length of the pending word, so the field can be passed to a contract taking a `ByteArray`
+//! rather than a layout only this crate reads.
+
+use crate::decoder::{Decode, Decoder};
+use crate::encoder::{encode_slice_len, Encode, Encoder};
+use crate::error::{DecodeError, EncodeError, PathSegment};
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+use starknet::core::types::FieldElement;
+use std::fmt;
+
+/// How `serialize_bytes` and `deserialize_byte_buf` encode the bytes, selected by the newtype
+/// name of the adapters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum BytesMode {
+    /// The length then one element per byte
+    #[default]
+    Elements,
+    /// The full 31 bytes words, the pending word and its length, the layout of a cairo
+    /// `ByteArray`
+    Bytes31,
+    /// The length then an array of 16 bytes words, the layout of [crate::proto::Bytes]
+    Bytes16,
+}
+
+const BYTES31_NAME: &str = "$stark_util::packed::bytes31";
+const BYTES16_NAME: &str = "$stark_util::packed::bytes16";
+
+impl BytesMode {
+    pub(crate) fn from_newtype_name(name: &str) -> Option<Self> {
+        match name {
+            BYTES31_NAME => Some(Self::Bytes31),
+            BYTES16_NAME => Some(Self::Bytes16),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encode<E: Encoder>(
+        self,
+        data: &[u8],
+        encoder: &mut E,
+    ) -> Result<(), EncodeError> {
+        match self {
+            Self::Elements => {
+                encode_slice_len(encoder, data.len())?;
+                encoder.describe("u8");
+                for b in data {
+                    b.encode(encoder)?;
+                }
+            }
+            Self::Bytes31 => {
+                let (words, pending) = data.split_at(data.len() / 31 * 31);
+                encoder.enter(PathSegment::Field("data"));
+                encode_slice_len(encoder, words.len() / 31)?;
+                encoder.describe("bytes31");
+                encode_words(words, 31, encoder);
+                encoder.leave();
+                encoder.enter(PathSegment::Field("pending_word"));
+                encoder.describe("felt252");
+                let mut word = [0u8; 32];
+                word[32 - pending.len()..].copy_from_slice(pending);
+                encoder.push_field_element(FieldElement::from_bytes_be(&word).expect("30 bytes"));
+                encoder.leave();
+                encoder.enter(PathSegment::Field("pending_word_len"));
+                encoder.describe("usize");
+                let result = pending.len().encode(encoder);
+                encoder.leave();
+                result?;
+            }
+            Self::Bytes16 => {
+                encode_slice_len(encoder, data.len())?;
+                encoder.enter(PathSegment::Field("words"));
+                encode_slice_len(encoder, data.len().div_ceil(16))?;
+                encoder.describe("u128");
+                encode_words(data, 16, encoder);
+                encoder.leave();
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decode<D: Decoder>(self, decoder: &mut D) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Self::Elements => Vec::decode(decoder),
+            Self::Bytes31 => {
+                let words = usize::decode(decoder)?;
+                let len = words
+                    .checked_mul(31)
+                    .ok_or(DecodeError::InvalidPackedBytes("word count overflow"))?;
+                let mut data = decode_words(len, 31, decoder)?;
+                let word = FieldElement::decode(decoder)?.to_bytes_be();
+                let pending = usize::decode(decoder)?;
+                if pending >= 31 {
+                    return Err(DecodeError::InvalidPackedBytes("pending word too long"));
+                }
+                let (high, bytes) = word.split_at(32 - pending);
+                if high.iter().any(|b| *b != 0) {
+                    return Err(DecodeError::InvalidPackedBytes("pending word too large"));
+                }
+                data.extend_from_slice(bytes);
+                Ok(data)
+            }
+            Self::Bytes16 => {
+                let len = usize::decode(decoder)?;
+                let words = usize::decode(decoder)?;
+                if words != len.div_ceil(16) {
+                    return Err(DecodeError::InvalidPackedBytes("word count mismatch"));
+                }
+                decode_words(len, 16, decoder)
+            }
+        }
+    }
+}
+
+fn encode_words<E: Encoder>(data: &[u8], word_bytes: usize, encoder: &mut E) {
+    for chunk in data.chunks(word_bytes) {
+        let mut word = [0u8; 32];
+        word[32 - word_bytes..32 - word_bytes + chunk.len()].copy_from_slice(chunk);
+        encoder.push_field_element(FieldElement::from_bytes_be(&word).expect("31 bytes at most"));
+    }
+}
+
+fn decode_words<D: Decoder>(
+    len: usize,
+    word_bytes: usize,
+    decoder: &mut D,
+) -> Result<Vec<u8>, DecodeError> {
+    // the length isn't trusted to allocate before the words are read
+    let mut data = vec![];
+    for i in 0..len.div_ceil(word_bytes) {
+        let word = FieldElement::decode(decoder)?.to_bytes_be();
+        let (high, bytes) = word.split_at(32 - word_bytes);
+        let used = (len - i * word_bytes).min(word_bytes);
+        if high.iter().chain(&bytes[used..]).any(|b| *b != 0) {
+            return Err(DecodeError::InvalidPackedBytes("non zero padding"));
+        }
+        data.extend_from_slice(&bytes[..used]);
+    }
+    Ok(data)
+}
+
+/// Serialize the bytes as a newtype named after the mode, whose value is serialized with
+/// `serialize_bytes`.
+fn serialize_newtype<S: Serializer>(
+    name: &'static str,
+    data: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    struct Bytes<'a>(&'a [u8]);
+
+    impl serde::Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    serializer.serialize_newtype_struct(name, &Bytes(data))
+}
+
+fn deserialize_newtype<'de, D: Deserializer<'de>>(
+    name: &'static str,
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "packed bytes")
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_byte_buf(self)
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        // self-describing formats may represent the bytes as a sequence
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut data = vec![];
+            while let Some(b) = seq.next_element()? {
+                data.push(b);
+            }
+            Ok(data)
+        }
+    }
+
+    deserializer.deserialize_newtype_struct(name, BytesVisitor)
+}
+
+/// The layout of a cairo `ByteArray`: the array of full 31 bytes words, the pending word of the
+/// remaining bytes as a number, then the number of remaining bytes.
+pub mod bytes31 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        data: impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_newtype(BYTES31_NAME, data.as_ref(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserialize_newtype(BYTES31_NAME, deserializer)
+    }
+}
+
+/// 16 bytes per `u128` element, with the layout of [crate::proto::Bytes]: the length, the number
+/// of words then the words.
+pub mod bytes16 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        data: impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_newtype(BYTES16_NAME, data.as_ref(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserialize_newtype(BYTES16_NAME, deserializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proto::{ByteArray, Bytes};
+    use crate::{from_slice, to_field_elements};
+    use serde::{Deserialize, Serialize};
+    use starknet::core::types::FieldElement;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Blob {
+        #[serde(with = "super::bytes31")]
+        data31: Vec<u8>,
+        #[serde(with = "super::bytes16")]
+        data16: Vec<u8>,
+        tail: u8,
+    }
+
+    #[test]
+    fn test_packed_bytes() {
+        let data: Vec<u8> = (1..=40).collect();
+        let blob = Blob {
+            data31: data.clone(),
+            data16: data.clone(),
+            tail: 7,
+        };
+        let encoded = to_field_elements(&blob).unwrap();
+        // 40 bytes are 1 full word and 9 pending bytes, or 3 u128 words
+        assert_eq!(encoded.len(), 4 + 2 + 3 + 1);
+        assert_eq!(encoded[0], FieldElement::ONE);
+        assert_eq!(
+            encoded[1],
+            FieldElement::from_byte_slice_be(&data[..31]).unwrap()
+        );
+        // the pending word is right-aligned, 0x20..0x28
+        assert_eq!(
+            encoded[2],
+            FieldElement::from_hex_be("0x202122232425262728").unwrap()
+        );
+        assert_eq!(encoded[3], FieldElement::from(9u8));
+        // the same layouts as `proto::ByteArray` and `proto::Bytes`
+        assert_eq!(
+            encoded[..4],
+            to_field_elements(ByteArray::from_bytes(&data)).unwrap()
+        );
+        assert_eq!(
+            encoded[4..9],
+            to_field_elements(Bytes::from_bytes(&data)).unwrap()
+        );
+        assert_eq!(from_slice::<Blob>(&encoded).unwrap(), blob);
+
+        let blob = Blob {
+            data31: data[..31].to_vec(),
+            ..blob
+        };
+        let encoded = to_field_elements(&blob).unwrap();
+        assert_eq!(encoded[2..4], [FieldElement::ZERO, FieldElement::ZERO]);
+        assert_eq!(from_slice::<Blob>(&encoded).unwrap(), blob);
+
+        let empty = Blob {
+            data31: vec![],
+            data16: vec![],
+            tail: 0,
+        };
+        let encoded = to_field_elements(&empty).unwrap();
+        assert_eq!(encoded, [0u8, 0, 0, 0, 0, 0].map(FieldElement::from));
+        assert_eq!(from_slice::<Blob>(&encoded).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_invalid_packed_bytes() {
+        let blob = Blob {
+            data31: vec![1, 2],
+            data16: vec![3],
+            tail: 0,
+        };
+        // the pending word is larger than its length
+        let mut encoded = to_field_elements(&blob).unwrap();
+        encoded[1] += FieldElement::from(1u32 << 16);
+        assert!(from_slice::<Blob>(&encoded).is_err());
+
+        let mut encoded = to_field_elements(&blob).unwrap();
+        encoded[2] = FieldElement::from(31u8);
+        assert!(from_slice::<Blob>(&encoded).is_err());
+
+        let mut encoded = to_field_elements(&blob).unwrap();
+        encoded[4] = FieldElement::TWO;
+        assert!(from_slice::<Blob>(&encoded).is_err());
+
+        // huge lengths fail on the missing words instead of allocating
+        let huge = 1u64 << 58;
+        let mut encoded = to_field_elements(&blob).unwrap();
+        encoded[0] = FieldElement::from(huge);
+        assert!(from_slice::<Blob>(&encoded).is_err());
+        let mut encoded = to_field_elements(&blob).unwrap();
+        encoded[3] = FieldElement::from(huge * 31);
+        encoded[4] = FieldElement::from((huge * 31).div_ceil(16));
+        assert!(from_slice::<Blob>(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_packed_bytes_json() {
+        let blob = Blob {
+            data31: vec![1, 2],
+            data16: vec![3],
+            tail: 4,
+        };
+        let json = serde_json::to_string(&blob).unwrap();
+        assert_eq!(json, r#"{"data31":[1,2],"data16":[3],"tail":4}"#);
+        assert_eq!(serde_json::from_str::<Blob>(&json).unwrap(), blob);
+    }
+}
@@ -1,10 +1,17 @@
+use crate::error::PubdataError;
 use crate::U256;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use starknet::core::types::FieldElement;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 
 // TODO: change some struct to this type of Bytes
 // TODO: rename Bytes
+/// Bytes packed into 16 bytes big-endian words, the last word is padded with zeros at the end.
+///
+/// The number of words must be `(size + 15) / 16`, which is checked when decoding.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(try_from = "RawBytes")]
 pub struct Bytes {
     /// the number of bytes in the Bytes
     pub size: usize,
@@ -12,6 +19,232 @@ pub struct Bytes {
     pub data: Vec<u128>,
 }
 
+/// The unchecked [Bytes] used for decoding
+#[derive(Deserialize)]
+#[serde(rename = "Bytes")]
+struct RawBytes {
+    size: usize,
+    data: Vec<u128>,
+}
+
+impl TryFrom<RawBytes> for Bytes {
+    type Error = PubdataError;
+
+    fn try_from(value: RawBytes) -> Result<Self, Self::Error> {
+        let bytes = Bytes {
+            size: value.size,
+            data: value.data,
+        };
+        bytes.validate()?;
+        Ok(bytes)
+    }
+}
+
+impl Bytes {
+    const WORD_BYTES: usize = 16;
+
+    /// Pack `data` into words.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let words = data
+            .chunks(Self::WORD_BYTES)
+            .map(|chunk| {
+                let mut word = [0u8; Self::WORD_BYTES];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u128::from_be_bytes(word)
+            })
+            .collect();
+        Self {
+            size: data.len(),
+            data: words,
+        }
+    }
+
+    /// Check that the number of words is consistent with the size.
+    pub fn validate(&self) -> Result<(), PubdataError> {
+        if self.data.len() != self.size.div_ceil(Self::WORD_BYTES) {
+            return Err(PubdataError::InvalidBytesSize {
+                size: self.size,
+                words: self.data.len(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Unpack the words into `size` bytes, the Bytes should be [valid](Bytes::validate).
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = self.data.iter().flat_map(|w| w.to_be_bytes()).collect();
+        data.resize(self.size, 0);
+        data
+    }
+
+    /// Read `len` bytes starting at `offset`.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Bytes, PubdataError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size => Ok(Self::from_bytes(&self.to_vec()[offset..end])),
+            _ => Err(PubdataError::LengthMismatch {
+                required: offset.saturating_add(len),
+                found: self.size,
+            }),
+        }
+    }
+
+    /// Append the bytes of `other`.
+    pub fn extend(&mut self, other: &Bytes) {
+        self.extend_from_slice(&other.to_vec())
+    }
+
+    /// Append `data`.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        let mut bytes = self.to_vec();
+        bytes.extend_from_slice(data);
+        *self = Self::from_bytes(&bytes);
+    }
+
+    /// Concatenate all the `bytes`.
+    pub fn concat<'a>(bytes: impl IntoIterator<Item = &'a Bytes>) -> Bytes {
+        let data: Vec<u8> = bytes.into_iter().flat_map(|b| b.to_vec()).collect();
+        Self::from_bytes(&data)
+    }
+
+    /// Convert to the cairo `ByteArray` representation.
+    pub fn to_byte_array(&self) -> ByteArray {
+        ByteArray::from_bytes(&self.to_vec())
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(value: &[u8]) -> Self {
+        Self::from_bytes(value)
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(value: Vec<u8>) -> Self {
+        Self::from_bytes(&value)
+    }
+}
+
+impl From<&Bytes> for Vec<u8> {
+    fn from(value: &Bytes) -> Self {
+        value.to_vec()
+    }
+}
+
+/// Hex string with "0x" prefix
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.to_vec()))
+    }
+}
+
+/// Parse hex string with "0x" prefix or not
+impl FromStr for Bytes {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix("0x").unwrap_or(s);
+        Ok(Self::from_bytes(&hex::decode(s)?))
+    }
+}
+
+/// A `bytes31` of cairo, stored in a field element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Bytes31(FieldElement);
+
+impl Bytes31 {
+    const BYTES: usize = 31;
+
+    /// Pack at most 31 bytes as a big-endian number.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() > Self::BYTES {
+            return None;
+        }
+        FieldElement::from_byte_slice_be(data).ok().map(Self)
+    }
+
+    /// The last `len` bytes of the big-endian number.
+    pub fn to_bytes(&self, len: usize) -> Vec<u8> {
+        let data = self.0.to_bytes_be();
+        data[32 - len.min(Self::BYTES)..].to_vec()
+    }
+}
+
+impl From<Bytes31> for FieldElement {
+    fn from(value: Bytes31) -> Self {
+        value.0
+    }
+}
+
+impl Serialize for Bytes31 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes31 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let element = FieldElement::from_hex_be(&s).map_err(de::Error::custom)?;
+        if element.to_bytes_be()[0] != 0 {
+            return Err(de::Error::custom("bytes31 out of range"));
+        }
+        Ok(Self(element))
+    }
+}
+
+/// The cairo `ByteArray`: full 31 bytes words followed by a pending word of less than 31 bytes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ByteArray {
+    pub data: Vec<Bytes31>,
+    pub pending_word: Bytes31,
+    pub pending_word_len: usize,
+}
+
+impl ByteArray {
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut chunks = data.chunks_exact(Bytes31::BYTES);
+        let words = chunks
+            .by_ref()
+            .map(|chunk| Bytes31::from_bytes(chunk).expect("31 bytes"))
+            .collect();
+        let pending = chunks.remainder();
+        Self {
+            data: words,
+            pending_word: Bytes31::from_bytes(pending).expect("less than 31 bytes"),
+            pending_word_len: pending.len(),
+        }
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, PubdataError> {
+        if self.pending_word_len >= Bytes31::BYTES {
+            return Err(PubdataError::InvalidPendingWordLen(self.pending_word_len));
+        }
+        let mut data: Vec<u8> = self
+            .data
+            .iter()
+            .flat_map(|w| w.to_bytes(Bytes31::BYTES))
+            .collect();
+        data.extend(self.pending_word.to_bytes(self.pending_word_len));
+        Ok(data)
+    }
+}
+
+impl TryFrom<&ByteArray> for Bytes {
+    type Error = PubdataError;
+
+    fn try_from(value: &ByteArray) -> Result<Self, Self::Error> {
+        Ok(Self::from_bytes(&value.to_vec()?))
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct TxHash(FieldElement);
 
@@ -88,6 +321,47 @@ pub struct ExecuteBlockInfo {
     pub pending_onchain_ops_pubdata: Vec<Bytes>,
 }
 
+/// The type of a zkLink operation, it's the first byte of the operation pubdata
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OpType {
+    Noop = 0,
+    Deposit = 1,
+    TransferToNew = 2,
+    Withdraw = 3,
+    Transfer = 4,
+    FullExit = 5,
+    ChangePubKey = 6,
+    ForcedExit = 7,
+    OrderMatching = 8,
+}
+
+impl TryFrom<u8> for OpType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let op_type = match value {
+            0 => Self::Noop,
+            1 => Self::Deposit,
+            2 => Self::TransferToNew,
+            3 => Self::Withdraw,
+            4 => Self::Transfer,
+            5 => Self::FullExit,
+            6 => Self::ChangePubKey,
+            7 => Self::ForcedExit,
+            8 => Self::OrderMatching,
+            _ => return Err(value),
+        };
+        Ok(op_type)
+    }
+}
+
+impl From<OpType> for u8 {
+    fn from(value: OpType) -> Self {
+        value as u8
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Deposit {
     // 1 byte, deposit from which chain that identified by l2 chain id
@@ -171,6 +445,7 @@ pub struct ChangePubKey {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::layout::FeltLayout;
     use crate::num::PrimitiveU256;
     use crate::{from_slice, to_field_elements};
 
@@ -184,13 +459,68 @@ mod test {
     #[test]
     fn test_serde_bytes() {
         let b = Bytes {
-            size: 1,
+            size: 33,
             data: vec![1, 2, 3],
         };
         let elements = to_field_elements(b.clone()).unwrap();
         assert_eq!(elements.len(), 5);
         let b2 = from_slice(&elements).unwrap();
         assert_eq!(b, b2);
+
+        let invalid = Bytes {
+            size: 1,
+            data: vec![1, 2, 3],
+        };
+        let elements = to_field_elements(invalid).unwrap();
+        assert!(from_slice::<Bytes>(&elements).is_err());
+    }
+
+    #[test]
+    fn test_bytes() {
+        let data: Vec<u8> = (1..=20).collect();
+        let b = Bytes::from_bytes(&data);
+        assert_eq!(b.size, 20);
+        assert_eq!(
+            b.data[0],
+            u128::from_be_bytes(data[..16].try_into().unwrap())
+        );
+        assert_eq!(b.data[1], 0x11121314 << 96);
+        assert!(b.validate().is_ok());
+        assert_eq!(b.to_vec(), data);
+
+        assert_eq!(b.slice(3, 15).unwrap().to_vec(), &data[3..18]);
+        assert!(b.slice(10, 11).is_err());
+
+        let mut b2 = b.clone();
+        b2.extend(&Bytes::from_bytes(&[21, 22]));
+        assert_eq!(b2.to_vec(), (1..=22).collect::<Vec<u8>>());
+        assert_eq!(Bytes::concat([&b, &Bytes::default(), &b]).size, 40);
+
+        let s = Bytes::from_bytes(&[0, 1, 0xab]).to_string();
+        assert_eq!(s, "0x0001ab");
+        assert_eq!(s.parse::<Bytes>().unwrap().to_vec(), vec![0, 1, 0xab]);
+        assert_eq!("ff".parse::<Bytes>().unwrap().to_vec(), vec![0xff]);
+        assert!("0xf".parse::<Bytes>().is_err());
+    }
+
+    #[test]
+    fn test_byte_array() {
+        let data: Vec<u8> = (0..70).collect();
+        let b = Bytes::from_bytes(&data);
+        let array = b.to_byte_array();
+        assert_eq!(array.data.len(), 2);
+        assert_eq!(array.pending_word_len, 8);
+        assert_eq!(Bytes::try_from(&array).unwrap(), b);
+
+        let elements = to_field_elements(&array).unwrap();
+        // length, 2 words, pending word, pending word length
+        assert_eq!(elements.len(), 5);
+        assert_eq!(elements[4], FieldElement::from(8u8));
+        let array2: ByteArray = from_slice(&elements).unwrap();
+        assert_eq!(array, array2);
+
+        let empty = ByteArray::from_bytes(&[]);
+        assert_eq!(empty.to_vec().unwrap(), Vec::<u8>::new());
     }
 
     #[test]
@@ -232,7 +562,10 @@ mod test {
             sync_hash: PrimitiveU256::from(3u8).into(),
         };
         let field_elements = to_field_elements(info.clone()).unwrap();
-        assert_eq!(field_elements.len(), 11);
+        assert_eq!(
+            StoredBlockInfo::felt_layout().unwrap().fixed_len(),
+            Some(field_elements.len())
+        );
         let info2 = from_slice(&field_elements).unwrap();
         assert_eq!(info, info2);
     }
@@ -0,0 +1,494 @@
+//! The big-endian pubdata layout of the zkLink operations used by the circuit.
+//!
+//! Every operation starts with its op type byte, followed by its fields in declaration order
+//! with the byte widths documented in [crate::proto], and is padded with zeros to a whole
+//! number of chunks.
+use crate::error::PubdataError;
+use crate::proto::{Bytes, ChangePubKey, Deposit, ForcedExit, FullExit, OpType, Withdraw};
+
+/// The number of bytes of a pubdata chunk
+pub const CHUNK_BYTES: usize = 23;
+/// The number of bytes of an address
+pub const ADDRESS_BYTES: usize = 32;
+/// The number of bytes of a rollup pubkey hash
+pub const PUBKEY_HASH_BYTES: usize = 20;
+
+/// A zkLink operation that can be packed into and parsed from pubdata.
+pub trait PubdataOp: Sized {
+    const OP_TYPE: OpType;
+    /// The number of chunks of the operation pubdata
+    const CHUNKS: usize;
+    /// The number of bytes of the operation pubdata
+    const SIZE: usize = Self::CHUNKS * CHUNK_BYTES;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError>;
+
+    /// Parse the operation from exactly [PubdataOp::SIZE] bytes.
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError>;
+}
+
+struct PubdataWriter {
+    data: Vec<u8>,
+}
+
+impl PubdataWriter {
+    fn new(op_type: OpType) -> Self {
+        Self {
+            data: vec![op_type.into()],
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    /// Write a hex string(with "0x" prefix or not) as a `len` bytes big-endian number
+    fn write_hex(&mut self, value: &str, len: usize) -> Result<&mut Self, PubdataError> {
+        let invalid = || PubdataError::InvalidHexString {
+            value: value.to_string(),
+            len,
+        };
+        let s = value.strip_prefix("0x").unwrap_or(value);
+        if s.len() > len * 2 {
+            return Err(invalid());
+        }
+        let padded = format!("{s:0>width$}", width = len * 2);
+        let bytes = hex::decode(padded).map_err(|_| invalid())?;
+        Ok(self.write(&bytes))
+    }
+
+    /// Pad the pubdata with zeros to `size` bytes
+    fn finish(mut self, size: usize) -> Vec<u8> {
+        debug_assert!(self.data.len() <= size);
+        self.data.resize(size, 0);
+        self.data
+    }
+}
+
+struct PubdataReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PubdataReader<'a> {
+    /// Check the length and op type of `data`, the reader starts after the op type byte.
+    fn new<T: PubdataOp>(data: &'a [u8]) -> Result<Self, PubdataError> {
+        if data.len() != T::SIZE {
+            return Err(PubdataError::LengthMismatch {
+                required: T::SIZE,
+                found: data.len(),
+            });
+        }
+        let expected = u8::from(T::OP_TYPE);
+        if data[0] != expected {
+            return Err(PubdataError::OpTypeMismatch {
+                expected,
+                found: data[0],
+            });
+        }
+        Ok(Self { data: &data[1..] })
+    }
+
+    fn read<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.data[..N]);
+        self.data = &self.data[N..];
+        bytes
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        u8::from_be_bytes(self.read())
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.read())
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.read())
+    }
+
+    fn read_u128(&mut self) -> u128 {
+        u128::from_be_bytes(self.read())
+    }
+
+    /// Read a `N` bytes number as a hex string, same as the `String` decoded from field elements
+    fn read_hex<const N: usize>(&mut self) -> String {
+        let s = hex::encode(self.read::<N>());
+        s.trim_start_matches('0').into()
+    }
+}
+
+impl PubdataOp for Deposit {
+    const OP_TYPE: OpType = OpType::Deposit;
+    const CHUNKS: usize = 3;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        let mut writer = PubdataWriter::new(Self::OP_TYPE);
+        writer
+            .write(&self.chain_id.to_be_bytes())
+            .write(&self.account_id.to_be_bytes())
+            .write(&self.sub_account_id.to_be_bytes())
+            .write(&self.token_id.to_be_bytes())
+            .write(&self.target_token_id.to_be_bytes())
+            .write(&self.amount.to_be_bytes())
+            .write_hex(&self.owner, ADDRESS_BYTES)?;
+        Ok(writer.finish(Self::SIZE))
+    }
+
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let mut reader = PubdataReader::new::<Self>(data)?;
+        Ok(Self {
+            chain_id: reader.read_u8(),
+            account_id: reader.read_u32(),
+            sub_account_id: reader.read_u8(),
+            token_id: reader.read_u16(),
+            target_token_id: reader.read_u16(),
+            amount: reader.read_u128(),
+            owner: reader.read_hex::<ADDRESS_BYTES>(),
+        })
+    }
+}
+
+impl PubdataOp for FullExit {
+    const OP_TYPE: OpType = OpType::FullExit;
+    const CHUNKS: usize = 3;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        let mut writer = PubdataWriter::new(Self::OP_TYPE);
+        writer
+            .write(&self.chain_id.to_be_bytes())
+            .write(&self.account_id.to_be_bytes())
+            .write(&self.sub_account_id.to_be_bytes())
+            .write_hex(&self.owner, ADDRESS_BYTES)?
+            .write(&self.token_id.to_be_bytes())
+            .write(&self.src_token_id.to_be_bytes())
+            .write(&self.amount.to_be_bytes());
+        Ok(writer.finish(Self::SIZE))
+    }
+
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let mut reader = PubdataReader::new::<Self>(data)?;
+        Ok(Self {
+            chain_id: reader.read_u8(),
+            account_id: reader.read_u32(),
+            sub_account_id: reader.read_u8(),
+            owner: reader.read_hex::<ADDRESS_BYTES>(),
+            token_id: reader.read_u16(),
+            src_token_id: reader.read_u16(),
+            amount: reader.read_u128(),
+        })
+    }
+}
+
+impl PubdataOp for Withdraw {
+    const OP_TYPE: OpType = OpType::Withdraw;
+    const CHUNKS: usize = 3;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        let mut writer = PubdataWriter::new(Self::OP_TYPE);
+        writer
+            .write(&self.chain_id.to_be_bytes())
+            .write(&self.account_id.to_be_bytes())
+            .write(&self.token_id.to_be_bytes())
+            .write(&self.amount.to_be_bytes())
+            .write_hex(&self.owner, ADDRESS_BYTES)?
+            .write(&self.nonce.to_be_bytes())
+            .write(&self.fast_withdraw_fee_rate.to_be_bytes());
+        Ok(writer.finish(Self::SIZE))
+    }
+
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let mut reader = PubdataReader::new::<Self>(data)?;
+        Ok(Self {
+            chain_id: reader.read_u8(),
+            account_id: reader.read_u32(),
+            token_id: reader.read_u16(),
+            amount: reader.read_u128(),
+            owner: reader.read_hex::<ADDRESS_BYTES>(),
+            nonce: reader.read_u32(),
+            fast_withdraw_fee_rate: reader.read_u16(),
+        })
+    }
+}
+
+impl PubdataOp for ForcedExit {
+    const OP_TYPE: OpType = OpType::ForcedExit;
+    const CHUNKS: usize = 3;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        let mut writer = PubdataWriter::new(Self::OP_TYPE);
+        writer
+            .write(&self.chain_id.to_be_bytes())
+            .write(&self.token_id.to_be_bytes())
+            .write(&self.amount.to_be_bytes())
+            .write_hex(&self.target, ADDRESS_BYTES)?;
+        Ok(writer.finish(Self::SIZE))
+    }
+
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let mut reader = PubdataReader::new::<Self>(data)?;
+        Ok(Self {
+            chain_id: reader.read_u8(),
+            token_id: reader.read_u16(),
+            amount: reader.read_u128(),
+            target: reader.read_hex::<ADDRESS_BYTES>(),
+        })
+    }
+}
+
+impl PubdataOp for ChangePubKey {
+    const OP_TYPE: OpType = OpType::ChangePubKey;
+    const CHUNKS: usize = 3;
+
+    fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        let mut writer = PubdataWriter::new(Self::OP_TYPE);
+        writer
+            .write(&self.chain_id.to_be_bytes())
+            .write(&self.account_id.to_be_bytes())
+            .write_hex(&self.pub_key_hash, PUBKEY_HASH_BYTES)?
+            .write_hex(&self.owner, ADDRESS_BYTES)?
+            .write(&self.nonce.to_be_bytes());
+        Ok(writer.finish(Self::SIZE))
+    }
+
+    fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let mut reader = PubdataReader::new::<Self>(data)?;
+        Ok(Self {
+            chain_id: reader.read_u8(),
+            account_id: reader.read_u32(),
+            pub_key_hash: reader.read_hex::<PUBKEY_HASH_BYTES>(),
+            owner: reader.read_hex::<ADDRESS_BYTES>(),
+            nonce: reader.read_u32(),
+        })
+    }
+}
+
+/// An operation of the block pubdata, dispatched on the op type byte
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// One chunk of zeros
+    Noop,
+    Deposit(Deposit),
+    Withdraw(Withdraw),
+    FullExit(FullExit),
+    ForcedExit(ForcedExit),
+    ChangePubKey(ChangePubKey),
+}
+
+impl Operation {
+    pub fn op_type(&self) -> OpType {
+        match self {
+            Operation::Noop => OpType::Noop,
+            Operation::Deposit(_) => OpType::Deposit,
+            Operation::Withdraw(_) => OpType::Withdraw,
+            Operation::FullExit(_) => OpType::FullExit,
+            Operation::ForcedExit(_) => OpType::ForcedExit,
+            Operation::ChangePubKey(_) => OpType::ChangePubKey,
+        }
+    }
+
+    /// The number of chunks of the operation with `op_type`
+    pub fn chunks(op_type: u8) -> Result<usize, PubdataError> {
+        let chunks = match OpType::try_from(op_type) {
+            Ok(OpType::Noop) => 1,
+            Ok(OpType::Deposit) => Deposit::CHUNKS,
+            Ok(OpType::Withdraw) => Withdraw::CHUNKS,
+            Ok(OpType::FullExit) => FullExit::CHUNKS,
+            Ok(OpType::ForcedExit) => ForcedExit::CHUNKS,
+            Ok(OpType::ChangePubKey) => ChangePubKey::CHUNKS,
+            _ => return Err(PubdataError::UnsupportedOpType(op_type)),
+        };
+        Ok(chunks)
+    }
+
+    pub fn to_pubdata(&self) -> Result<Vec<u8>, PubdataError> {
+        match self {
+            Operation::Noop => Ok(vec![0; CHUNK_BYTES]),
+            Operation::Deposit(op) => op.to_pubdata(),
+            Operation::Withdraw(op) => op.to_pubdata(),
+            Operation::FullExit(op) => op.to_pubdata(),
+            Operation::ForcedExit(op) => op.to_pubdata(),
+            Operation::ChangePubKey(op) => op.to_pubdata(),
+        }
+    }
+
+    /// Parse one operation from the pubdata, the size of `data` must match the op type.
+    pub fn from_pubdata(data: &[u8]) -> Result<Self, PubdataError> {
+        let op_type = *data.first().ok_or(PubdataError::LengthMismatch {
+            required: CHUNK_BYTES,
+            found: 0,
+        })?;
+        let op = match OpType::try_from(op_type) {
+            Ok(OpType::Noop) => {
+                if data.len() != CHUNK_BYTES {
+                    return Err(PubdataError::LengthMismatch {
+                        required: CHUNK_BYTES,
+                        found: data.len(),
+                    });
+                }
+                Operation::Noop
+            }
+            Ok(OpType::Deposit) => Operation::Deposit(Deposit::from_pubdata(data)?),
+            Ok(OpType::Withdraw) => Operation::Withdraw(Withdraw::from_pubdata(data)?),
+            Ok(OpType::FullExit) => Operation::FullExit(FullExit::from_pubdata(data)?),
+            Ok(OpType::ForcedExit) => Operation::ForcedExit(ForcedExit::from_pubdata(data)?),
+            Ok(OpType::ChangePubKey) => Operation::ChangePubKey(ChangePubKey::from_pubdata(data)?),
+            _ => return Err(PubdataError::UnsupportedOpType(op_type)),
+        };
+        Ok(op)
+    }
+
+    /// Concatenate the pubdata of all the operations of a block.
+    pub fn to_block_pubdata(ops: &[Operation]) -> Result<Vec<u8>, PubdataError> {
+        let mut data = vec![];
+        for op in ops {
+            data.extend(op.to_pubdata()?);
+        }
+        Ok(data)
+    }
+
+    /// Split the pubdata of a block into operations.
+    pub fn from_block_pubdata(data: &[u8]) -> Result<Vec<Operation>, PubdataError> {
+        let mut ops = vec![];
+        let mut rest = data;
+        while !rest.is_empty() {
+            let size = Self::chunks(rest[0])? * CHUNK_BYTES;
+            if rest.len() < size {
+                return Err(PubdataError::LengthMismatch {
+                    required: size,
+                    found: rest.len(),
+                });
+            }
+            let (op_data, remaining) = rest.split_at(size);
+            ops.push(Self::from_pubdata(op_data)?);
+            rest = remaining;
+        }
+        Ok(ops)
+    }
+
+    /// Pack the pubdata of a block into [Bytes].
+    pub fn to_block_bytes(ops: &[Operation]) -> Result<Bytes, PubdataError> {
+        Ok(Bytes::from_bytes(&Self::to_block_pubdata(ops)?))
+    }
+
+    /// Parse the operations from the pubdata of a block stored in [Bytes].
+    pub fn from_block_bytes(bytes: &Bytes) -> Result<Vec<Operation>, PubdataError> {
+        bytes.validate()?;
+        Self::from_block_pubdata(&bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OWNER: &str = "9e290521bb937cebdbd1b5636037f089f7bf34de51f9fc019b07cdb8ed98a1";
+
+    fn deposit() -> Deposit {
+        Deposit {
+            chain_id: 1,
+            account_id: 2,
+            sub_account_id: 3,
+            token_id: 4,
+            target_token_id: 5,
+            amount: 6,
+            owner: OWNER.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_deposit_pubdata() {
+        let deposit = deposit();
+        let data = deposit.to_pubdata().unwrap();
+        assert_eq!(data.len(), Deposit::SIZE);
+        assert_eq!(&data[..12], &[1, 1, 0, 0, 0, 2, 3, 0, 4, 0, 5, 0]);
+        assert_eq!(data[26], 6);
+        assert_eq!(hex::encode(&data[27..59]), format!("00{OWNER}"));
+        assert!(data[59..].iter().all(|b| *b == 0));
+        assert_eq!(Deposit::from_pubdata(&data).unwrap(), deposit);
+    }
+
+    #[test]
+    fn test_ops_pubdata() {
+        let ops = vec![
+            Operation::Deposit(deposit()),
+            Operation::Noop,
+            Operation::Withdraw(Withdraw {
+                chain_id: 1,
+                account_id: 2,
+                token_id: 3,
+                amount: u128::MAX,
+                owner: OWNER.to_string(),
+                nonce: 4,
+                fast_withdraw_fee_rate: 5,
+            }),
+            Operation::FullExit(FullExit {
+                chain_id: 1,
+                account_id: 2,
+                sub_account_id: 3,
+                owner: OWNER.to_string(),
+                token_id: 4,
+                src_token_id: 5,
+                amount: 6,
+            }),
+            Operation::ForcedExit(ForcedExit {
+                chain_id: 1,
+                token_id: 2,
+                amount: 3,
+                target: format!("0x{OWNER}"),
+            }),
+            Operation::ChangePubKey(ChangePubKey {
+                chain_id: 1,
+                account_id: 2,
+                pub_key_hash: "1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b".to_string(),
+                owner: OWNER.to_string(),
+                nonce: 3,
+            }),
+        ];
+        let data = Operation::to_block_pubdata(&ops).unwrap();
+        assert_eq!(data.len(), 16 * CHUNK_BYTES);
+        for op in &ops {
+            assert_eq!(op.to_pubdata().unwrap()[0], u8::from(op.op_type()));
+        }
+
+        let bytes = Operation::to_block_bytes(&ops).unwrap();
+        assert_eq!(bytes.size, data.len());
+        assert_eq!(bytes.data.len(), 23);
+        let ops2 = Operation::from_block_bytes(&bytes).unwrap();
+        // the decoded address has no "0x" prefix
+        let mut expected = ops;
+        if let Operation::ForcedExit(op) = &mut expected[4] {
+            op.target = OWNER.to_string();
+        }
+        assert_eq!(ops2, expected);
+    }
+
+    #[test]
+    fn test_invalid_pubdata() {
+        let mut data = deposit().to_pubdata().unwrap();
+        assert_eq!(
+            FullExit::from_pubdata(&data),
+            Err(PubdataError::OpTypeMismatch {
+                expected: 5,
+                found: 1
+            })
+        );
+        data.pop();
+        assert_eq!(
+            Operation::from_block_pubdata(&data),
+            Err(PubdataError::LengthMismatch {
+                required: Deposit::SIZE,
+                found: Deposit::SIZE - 1
+            })
+        );
+        assert_eq!(
+            Operation::from_pubdata(&[4; CHUNK_BYTES]),
+            Err(PubdataError::UnsupportedOpType(4))
+        );
+
+        let mut deposit = deposit();
+        deposit.owner = "0xzz".to_string();
+        assert!(deposit.to_pubdata().is_err());
+    }
+}
@@ -1,15 +1,54 @@
 mod impl_tuples;
 
-use crate::encoder::{Encode, Encoder};
-use crate::error::EncodeError;
+use crate::array::FELTS_NAME;
+use crate::encoder::{
+    describe_type, encode_map_entries, encode_slice_len, Encode, EncodedEntry, Encoder,
+    EncoderImpl, OPTION_NONE, OPTION_SOME,
+};
+use crate::error::{EncodeError, PathSegment};
+use crate::packed::BytesMode;
+use crate::serde::{with_native_config, NATIVE_NAME};
+use crate::sorted_set::SORTED_SET_NAME;
+use crate::versioned::VERSIONED_NAME;
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant,
 };
 use serde::{ser, Serialize};
+use starknet::core::types::FieldElement;
+use std::cell::Cell;
+use std::rc::Rc;
 
 pub(crate) struct SerdeEncoder<'a, ENC: Encoder> {
     pub enc: &'a mut ENC,
+    /// The encoding of `serialize_bytes`
+    bytes: BytesMode,
+    /// Whether the sequence is a set of [crate::sorted_set], which is ordered like a map
+    sorted: bool,
+    /// Set once a [crate::array::Felts] is serialized, no element may follow it
+    felts_end: Rc<Cell<bool>>,
+}
+
+impl<'a, ENC: Encoder> SerdeEncoder<'a, ENC> {
+    pub(crate) fn new(enc: &'a mut ENC) -> Self {
+        Self {
+            enc,
+            bytes: BytesMode::default(),
+            sorted: false,
+            felts_end: Rc::default(),
+        }
+    }
+
+    fn describe(&mut self, type_name: &str) {
+        self.enc.describe(type_name)
+    }
+
+    /// Describe the variant, its name is only formatted if the encoder wants descriptions.
+    fn describe_variant(&mut self, name: &str, variant: &str) {
+        if self.enc.wants_descriptions() {
+            self.enc.describe(&format!("{name}::{variant}"));
+        }
+    }
 }
 
 impl<'a, ENC> ser::Serializer for SerdeEncoder<'a, ENC>
@@ -20,85 +59,103 @@ where
     // The error type when some error occurs during serialization.
     type Error = EncodeError;
 
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
+    type SerializeSeq = Compound<'a, ENC>;
+    type SerializeTuple = Compound<'a, ENC>;
+    type SerializeTupleStruct = Compound<'a, ENC>;
+    type SerializeTupleVariant = Compound<'a, ENC>;
+    type SerializeMap = MapCompound<'a, ENC>;
+    type SerializeStruct = Compound<'a, ENC>;
+    type SerializeStructVariant = Compound<'a, ENC>;
 
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+    fn serialize_bool(mut self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.describe("bool");
         v.encode(self.enc)
     }
 
-    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i8(mut self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.describe("i8");
         v.encode(self.enc)
     }
 
-    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i16(mut self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.describe("i16");
         v.encode(self.enc)
     }
 
-    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i32(mut self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.describe("i32");
         v.encode(self.enc)
     }
 
-    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i64(mut self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.describe("i64");
         v.encode(self.enc)
     }
 
-    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+    fn serialize_i128(mut self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.describe("i128");
         v.encode(self.enc)
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_f32(mut self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.describe("f32");
         v.encode(self.enc)
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_f64(mut self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.describe("f64");
         v.encode(self.enc)
     }
 
-    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+    fn serialize_char(mut self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.describe("char");
         v.encode(self.enc)
     }
 
-    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+    fn serialize_str(mut self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.describe("felt252");
+        // strings are the hex of a single element
         v.encode(self.enc)
     }
 
-    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u8(mut self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.describe("u8");
         v.encode(self.enc)
     }
 
-    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u16(mut self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.describe("u16");
         v.encode(self.enc)
     }
 
-    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u32(mut self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.describe("u32");
         v.encode(self.enc)
     }
 
-    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u64(mut self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.describe("u64");
         v.encode(self.enc)
     }
 
     serde::serde_if_integer128! {
-        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        fn serialize_u128(mut self, v: u128) -> Result<Self::Ok, Self::Error> {
+            self.describe("u128");
             v.encode(self.enc)
         }
     }
 
-    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        0u8.encode(self.enc)
+    fn serialize_none(mut self) -> Result<Self::Ok, Self::Error> {
+        self.describe("Option::None");
+        OPTION_NONE.encode(self.enc)
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T: ?Sized>(mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        1u8.encode(self.enc)?;
+        self.describe("Option::Some");
+        OPTION_SOME.encode(self.enc)?;
         value.serialize(self)
     }
 
@@ -107,47 +164,75 @@ where
     }
 
     fn serialize_unit_variant(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
+        self.describe_variant(name, variant);
         variant_index.encode(self.enc)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        value.serialize(self)
+        if name == SORTED_SET_NAME {
+            return value.serialize(SerdeEncoder {
+                sorted: true,
+                ..self
+            });
+        }
+        if name == FELTS_NAME {
+            let felts_end = self.felts_end.clone();
+            value.serialize(self)?;
+            felts_end.set(true);
+            return Ok(());
+        }
+        if name == NATIVE_NAME || name == VERSIONED_NAME {
+            let config = self.enc.config();
+            return with_native_config(config, || value.serialize(self));
+        }
+        match BytesMode::from_newtype_name(name) {
+            Some(bytes) => value.serialize(SerdeEncoder { bytes, ..self }),
+            None => value.serialize(self),
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
+        self.describe_variant(name, variant);
         variant_index.encode(self.enc)?;
         value.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         let len = len.ok_or(EncodeError::SequenceMustHaveLength)?;
-        len.encode(self.enc)?;
-        Ok(Compound { enc: self.enc })
+        if self.sorted {
+            // the length is written with the sorted elements on `end`
+            return Ok(Compound {
+                set: Some(Default::default()),
+                ..Compound::new(self.enc, self.felts_end)
+            });
+        }
+        encode_slice_len(self.enc, len)?;
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
     fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        Ok(self)
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
     fn serialize_tuple_struct(
@@ -155,24 +240,30 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        Ok(Compound { enc: self.enc })
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
     fn serialize_tuple_variant(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.describe_variant(name, variant);
         variant_index.encode(self.enc)?;
-        Ok(Compound { enc: self.enc })
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        let len = len.ok_or(EncodeError::SequenceMustHaveLength)?;
-        len.encode(self.enc)?;
-        Ok(Compound { enc: self.enc })
+        // `#[serde(flatten)]` serializes the struct as a map without length
+        len.ok_or(EncodeError::MapMustHaveLength)?;
+        Ok(MapCompound {
+            enc: self.enc,
+            felts_end: self.felts_end,
+            entries: vec![],
+            types: Default::default(),
+        })
     }
 
     fn serialize_struct(
@@ -180,34 +271,37 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(Compound { enc: self.enc })
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
     fn serialize_struct_variant(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.describe_variant(name, variant);
         variant_index.encode(self.enc)?;
-        Ok(Compound { enc: self.enc })
+        Ok(Compound::new(self.enc, self.felts_end))
     }
 
-    fn collect_str<T: ?Sized>(self, v: &T) -> Result<Self::Ok, Self::Error>
+    fn collect_str<T: ?Sized>(mut self, v: &T) -> Result<Self::Ok, Self::Error>
     where
         T: core::fmt::Display,
     {
+        self.describe("felt252");
         let s = v.to_string();
         s.encode(self.enc)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        v.encode(self.enc)
+        self.bytes.encode(v, self.enc)
     }
 
+    // like the cairo `()`, there is nothing to encode
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        self.serialize_u8(0)
+        Ok(())
     }
 
     fn is_human_readable(&self) -> bool {
@@ -215,7 +309,63 @@ where
     }
 }
 
-type Compound<'a, ENC> = SerdeEncoder<'a, ENC>;
+pub(crate) struct Compound<'a, ENC: Encoder> {
+    enc: &'a mut ENC,
+    felts_end: Rc<Cell<bool>>,
+    /// The index of the next element, used in the path of errors
+    index: usize,
+    /// The encoded elements of a sorted set and their rust type, written on `end`
+    set: Option<(Vec<EncodedEntry>, String)>,
+}
+
+impl<'a, ENC: Encoder> Compound<'a, ENC> {
+    fn new(enc: &'a mut ENC, felts_end: Rc<Cell<bool>>) -> Self {
+        Self {
+            enc,
+            felts_end,
+            index: 0,
+            set: None,
+        }
+    }
+
+    /// Serialize the next element, errors are annotated with its index.
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), EncodeError>
+    where
+        T: Serialize,
+    {
+        let index = self.index;
+        self.index += 1;
+        if let Some((entries, type_name)) = &mut self.set {
+            let element = serialize_to_vec(&*self.enc, index, value)?;
+            entries.push((element, vec![]));
+            *type_name = describe_type::<_, T>(&*self.enc);
+            return Ok(());
+        }
+        self.serialize_field(PathSegment::Index(index), value)
+    }
+
+    /// Serialize a field, errors are annotated with `segment`.
+    fn serialize_field<T: ?Sized>(
+        &mut self,
+        segment: PathSegment,
+        value: &T,
+    ) -> Result<(), EncodeError>
+    where
+        T: Serialize,
+    {
+        // the decoder reads the remaining elements into `Felts`
+        if self.felts_end.get() {
+            return Err(EncodeError::FeltsNotLast.in_field(segment));
+        }
+        self.enc.enter(segment.clone());
+        let result = value.serialize(SerdeEncoder {
+            felts_end: self.felts_end.clone(),
+            ..SerdeEncoder::new(self.enc)
+        });
+        self.enc.leave();
+        result.map_err(|e| e.in_field(segment))
+    }
+}
 
 impl<'a, ENC: Encoder> SerializeSeq for Compound<'a, ENC> {
     type Ok = ();
@@ -225,11 +375,14 @@ impl<'a, ENC: Encoder> SerializeSeq for Compound<'a, ENC> {
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        Compound::serialize_element(self, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        match self.set {
+            Some((entries, type_name)) => encode_map_entries(self.enc, entries, (&type_name, "()")),
+            None => Ok(()),
+        }
     }
 }
 
@@ -241,7 +394,7 @@ impl<'a, ENC: Encoder> SerializeTuple for Compound<'a, ENC> {
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        Compound::serialize_element(self, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -257,7 +410,7 @@ impl<'a, ENC: Encoder> SerializeTupleStruct for Compound<'a, ENC> {
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        self.serialize_element(value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -273,7 +426,7 @@ impl<'a, ENC: Encoder> SerializeTupleVariant for Compound<'a, ENC> {
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        self.serialize_element(value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -281,7 +434,33 @@ impl<'a, ENC: Encoder> SerializeTupleVariant for Compound<'a, ENC> {
     }
 }
 
-impl<'a, ENC: Encoder> SerializeMap for Compound<'a, ENC> {
+/// Buffers the entries of a map, which are written ordered by their encoded keys on `end`, so
+/// the encoding doesn't depend on the iteration order of the map.
+pub(crate) struct MapCompound<'a, ENC: Encoder> {
+    enc: &'a mut ENC,
+    felts_end: Rc<Cell<bool>>,
+    entries: Vec<EncodedEntry>,
+    /// The rust types of the keys and values, which describe the entries
+    types: (String, String),
+}
+
+/// Serialize the element or map entry part at `index` on its own, with the config of `enc`.
+fn serialize_to_vec<ENC: Encoder, T: ?Sized>(
+    enc: &ENC,
+    index: usize,
+    value: &T,
+) -> Result<Vec<FieldElement>, EncodeError>
+where
+    T: Serialize,
+{
+    let mut buf = EncoderImpl::new(enc.config());
+    value
+        .serialize(SerdeEncoder::new(&mut buf))
+        .map_err(|e| e.in_field(PathSegment::Index(index)))?;
+    Ok(buf.filed_elements)
+}
+
+impl<'a, ENC: Encoder> SerializeMap for MapCompound<'a, ENC> {
     type Ok = ();
     type Error = EncodeError;
 
@@ -289,18 +468,28 @@ impl<'a, ENC: Encoder> SerializeMap for Compound<'a, ENC> {
     where
         T: Serialize,
     {
-        key.serialize(SerdeEncoder { enc: self.enc })
+        let key = serialize_to_vec(&*self.enc, self.entries.len(), key)?;
+        self.entries.push((key, vec![]));
+        self.types.0 = describe_type::<_, T>(&*self.enc);
+        Ok(())
     }
 
     fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        // the entry was pushed by `serialize_key`
+        let index = self.entries.len() - 1;
+        self.entries[index].1 = serialize_to_vec(&*self.enc, index, value)?;
+        self.types.1 = describe_type::<_, T>(&*self.enc);
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        if self.felts_end.get() {
+            return Err(EncodeError::FeltsNotLast);
+        }
+        encode_map_entries(self.enc, self.entries, (&self.types.0, &self.types.1))
     }
 }
 
@@ -310,13 +499,22 @@ impl<'a, ENC: Encoder> SerializeStruct for Compound<'a, ENC> {
 
     fn serialize_field<T: ?Sized>(
         &mut self,
-        _key: &'static str,
+        key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        Compound::serialize_field(self, PathSegment::Field(key), value)
+    }
+
+    /// The decoder reads fields by position, so skipped fields can't be decoded.
+    ///
+    /// Only `#[serde(skip_serializing_if)]` calls this, a `#[serde(skip_serializing)]` field is
+    /// never seen by the serializer and the fields after it are decoded from the wrong elements.
+    /// `#[serde(skip)]` is fine as the field is skipped by the decoder as well.
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        Err(EncodeError::SkippedField(key))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -330,13 +528,18 @@ impl<'a, ENC: Encoder> SerializeStructVariant for Compound<'a, ENC> {
 
     fn serialize_field<T: ?Sized>(
         &mut self,
-        _key: &'static str,
+        key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        value.serialize(SerdeEncoder { enc: self.enc })
+        Compound::serialize_field(self, PathSegment::Field(key), value)
+    }
+
+    /// The decoder reads fields by position, so skipped fields can't be decoded
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        Err(EncodeError::SkippedField(key))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
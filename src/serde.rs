@@ -1,13 +1,29 @@
-use crate::decoder::{Decode, Decoder};
+//! Bridges between serde and the native [Encode] and [Decode] traits.
+//!
+//! A hand-written [Encode] or [Decode] impl embeds serde types with [Compat] and [BorrowCompat],
+//! and a serde type embeds native types with [Native]. Both paths produce the same elements.
+
+use crate::array::Felt;
+use crate::config::Config;
+use crate::decoder::{Decode, Decoder, DecoderImpl, Reader};
 use crate::der::de_owned::SerdeDecoder;
-use crate::encoder::{Encode, Encoder};
-use crate::error::DecodeError;
+use crate::encoder::{Encode, Encoder, EncoderImpl};
+use crate::error::{DecodeError, EncodeError};
 use crate::ser::SerdeEncoder;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::{self, SerializeTuple};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::types::FieldElement;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::marker::PhantomData;
 
 /// Wrapper struct that implements [Decode] and [Encode] on any type that implements serde's [DeserializeOwned] and [Serialize] respectively.
 ///
 /// This works for most types, but if you're dealing with borrowed data consider using [BorrowCompat] instead.
 ///
+/// [DeserializeOwned]: serde::de::DeserializeOwned
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Compat<T>(pub T);
 
 impl<T> Decode for Compat<T>
@@ -15,18 +31,359 @@ where
     T: serde::de::DeserializeOwned,
 {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let serde_decoder = SerdeDecoder { de: decoder };
+        let serde_decoder = SerdeDecoder::new(decoder);
         T::deserialize(serde_decoder).map(Compat)
     }
 }
 
 impl<T> Encode for Compat<T>
 where
-    T: serde::Serialize,
+    T: Serialize,
 {
-    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), crate::error::EncodeError> {
-        let serializer = SerdeEncoder { enc: encoder };
-        self.0.serialize(serializer)?;
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.0.serialize(SerdeEncoder::new(encoder))
+    }
+}
+
+/// Wrapper struct that implements [Encode] on a reference to any type that implements serde's
+/// [Serialize], including unsized types like `str` and slices.
+///
+/// A hand-written [Encode] impl uses it to encode a field without cloning it into a [Compat].
+#[derive(Debug)]
+pub struct BorrowCompat<'a, T: ?Sized>(pub &'a T);
+
+impl<T> Encode for BorrowCompat<'_, T>
+where
+    T: Serialize + ?Sized,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.0.serialize(SerdeEncoder::new(encoder))
+    }
+}
+
+/// Wrapper struct that implements serde's [Serialize] and [Deserialize] on any type that
+/// implements [Encode] and [Decode] respectively, the inverse of [Compat].
+///
+/// The value is serialized as its elements, so it's encoded as the elements themselves with the
+/// [Config] of the outer encoder or decoder, and self-describing formats get an array of `0x`
+/// prefixed hex strings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Native<T>(pub T);
+
+/// The newtype name of [Native], the serde encoder and decoder hand it their [Config]
+pub(crate) const NATIVE_NAME: &str = "$stark_util::Native";
+
+thread_local! {
+    static NATIVE_CONFIG: Cell<Option<Config>> = const { Cell::new(None) };
+    /// The error of the last [Native] decoded with a [Config] of [with_native_config], which the
+    /// serde decoder returns instead of the message its [de::Error] is built from
+    static NATIVE_ERROR: RefCell<Option<DecodeError>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `config` as the [Config] of the [Native] or [crate::versioned::Versioned] it
+/// serializes or deserializes.
+pub(crate) fn with_native_config<R>(config: Config, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Config>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            NATIVE_CONFIG.with(|c| c.set(self.0));
+        }
+    }
+
+    let _restore = Restore(NATIVE_CONFIG.with(|c| c.replace(Some(config))));
+    f()
+}
+
+/// Run `f`, which deserializes a [Native] with `config` after `offset` elements were read, its
+/// decode error is returned with the offsets counted from the start of the outer decoder.
+pub(crate) fn decode_native<R>(
+    config: Config,
+    offset: usize,
+    f: impl FnOnce() -> Result<R, DecodeError>,
+) -> Result<R, DecodeError> {
+    NATIVE_ERROR.with(|e| e.take());
+    with_native_config(config, f).map_err(|e| match NATIVE_ERROR.with(|e| e.take()) {
+        Some(native) => native.shift_offset(offset),
+        None => e,
+    })
+}
+
+/// The [Config] given by [with_native_config], `None` for other formats.
+pub(crate) fn native_config() -> Option<Config> {
+    NATIVE_CONFIG.with(|c| c.take())
+}
+
+impl<T: Encode> Serialize for Native<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Elements<'a, T>(&'a T);
+
+        impl<T: Encode> Serialize for Elements<'_, T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut encoder = EncoderImpl::new(native_config().unwrap_or_default());
+                self.0.encode(&mut encoder).map_err(ser::Error::custom)?;
+                let elements = encoder.filed_elements;
+                let mut tuple = serializer.serialize_tuple(elements.len())?;
+                for element in elements {
+                    tuple.serialize_element(&Felt(element))?;
+                }
+                tuple.end()
+            }
+        }
+
+        serializer.serialize_newtype_struct(NATIVE_NAME, &Elements(&self.0))
+    }
+}
+
+impl<'de, T: Decode> Deserialize<'de> for Native<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NativeVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Decode> Visitor<'de> for NativeVisitor<T> {
+            type Value = Native<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "the elements of {}", std::any::type_name::<T>())
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_seq(self)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                let config = native_config();
+                let mut reader = SeqReader {
+                    seq,
+                    buf: vec![],
+                    error: None,
+                };
+                let mut decoder = DecoderImpl::with_config(&mut reader, config.unwrap_or_default());
+                let value =
+                    T::decode(&mut decoder).map_err(|e| e.at_offset(decoder.field_elements_read()));
+                match (reader.error, value) {
+                    (Some(e), _) => Err(e),
+                    (None, Ok(value)) => Ok(Native(value)),
+                    (None, Err(e)) => {
+                        let error = de::Error::custom(&e);
+                        if config.is_some() {
+                            NATIVE_ERROR.with(|native| native.replace(Some(e)));
+                        }
+                        Err(error)
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NATIVE_NAME, NativeVisitor(PhantomData))
+    }
+}
+
+/// A [Reader] that pulls the elements from a serde sequence as they are read.
+struct SeqReader<'de, A: SeqAccess<'de>> {
+    seq: A,
+    buf: Vec<FieldElement>,
+    /// The error of the sequence, which is reported instead of the one of the decoder
+    error: Option<A::Error>,
+}
+
+impl<'de, A: SeqAccess<'de>> SeqReader<'de, A> {
+    /// Pull elements until `n` are buffered, returns `false` if the sequence ends first.
+    fn fill(&mut self, n: usize) -> bool {
+        while self.buf.len() < n && self.error.is_none() {
+            match self.seq.next_element::<Felt>() {
+                Ok(Some(element)) => self.buf.push(element.0),
+                Ok(None) => return false,
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self.buf.len() >= n
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> Reader for SeqReader<'de, A> {
+    fn read(&mut self, bytes: &mut [FieldElement]) -> Result<(), DecodeError> {
+        if !self.fill(bytes.len()) {
+            return Err(DecodeError::UnexpectedEnd {
+                additional: bytes.len() - self.buf.len(),
+            });
+        }
+        bytes.copy_from_slice(&self.buf[..bytes.len()]);
+        self.buf.drain(..bytes.len());
         Ok(())
     }
+
+    fn peek_read(&mut self, n: usize) -> Option<&[FieldElement]> {
+        if self.fill(n) {
+            Some(&self.buf[..n])
+        } else {
+            None
+        }
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.buf.drain(..n.min(self.buf.len()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::SliceReader;
+    use crate::proto::{Bytes, CommitBlockInfo, OnchainOperationData};
+    use crate::{from_slice, to_field_elements, U256};
+
+    /// A struct with hand-written impls that embeds a serde type.
+    #[derive(Debug, PartialEq)]
+    struct Commit {
+        chain_id: u8,
+        info: Compat<CommitBlockInfo>,
+    }
+
+    impl Encode for Commit {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.chain_id.encode(encoder)?;
+            BorrowCompat(&self.info.0).encode(encoder)
+        }
+    }
+
+    impl Decode for Commit {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Self {
+                chain_id: Decode::decode(decoder)?,
+                info: Decode::decode(decoder)?,
+            })
+        }
+    }
+
+    fn commit_block_info() -> CommitBlockInfo {
+        CommitBlockInfo {
+            new_state_hash: U256::from(7u8),
+            public_data: Bytes::from_bytes(&[1, 2, 3]),
+            timestamp: 10,
+            onchain_operations: vec![OnchainOperationData {
+                eth_witness: Bytes::from_bytes(&[4; 20]),
+                public_data_offset: 2,
+            }],
+            block_number: 5,
+            fee_account: 3,
+        }
+    }
+
+    fn encode<T: Encode>(value: &T) -> Vec<FieldElement> {
+        let mut encoder = EncoderImpl::new(Default::default());
+        value.encode(&mut encoder).unwrap();
+        encoder.filed_elements
+    }
+
+    #[test]
+    fn test_compat() {
+        let commit = Commit {
+            chain_id: 1,
+            info: Compat(commit_block_info()),
+        };
+        let encoded = encode(&commit);
+        // the same elements as the serde path
+        assert_eq!(
+            encoded,
+            to_field_elements((1u8, commit_block_info())).unwrap()
+        );
+        let mut decoder = DecoderImpl::new(SliceReader::new(&encoded));
+        assert_eq!(Commit::decode(&mut decoder).unwrap(), commit);
+        assert_eq!(decoder.field_elements_read(), encoded.len());
+    }
+
+    #[test]
+    fn test_native() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Batch {
+            commit: Native<Commit>,
+            tail: u64,
+        }
+        let batch = Batch {
+            commit: Native(Commit {
+                chain_id: 2,
+                info: Compat(commit_block_info()),
+            }),
+            tail: 9,
+        };
+        let encoded = to_field_elements(&batch).unwrap();
+        assert_eq!(
+            encoded,
+            to_field_elements((2u8, commit_block_info(), 9u64)).unwrap()
+        );
+        assert_eq!(from_slice::<Batch>(&encoded).unwrap(), batch);
+        assert!(from_slice::<Batch>(&encoded[..encoded.len() - 2]).is_err());
+
+        let json = serde_json::to_string(&batch).unwrap();
+        assert_eq!(serde_json::from_str::<Batch>(&json).unwrap(), batch);
+    }
+
+    #[test]
+    fn test_native_error_location() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Tagged {
+            tag: u64,
+            commit: Native<Commit>,
+        }
+        let tagged = Tagged {
+            tag: 1,
+            commit: Native(Commit {
+                chain_id: 2,
+                info: Compat(commit_block_info()),
+            }),
+        };
+        let mut encoded = to_field_elements(&tagged).unwrap();
+
+        // the offsets count the elements before the native value
+        encoded[1] = FieldElement::from(256u16);
+        let error = from_slice::<Tagged>(&encoded).unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            DecodeError::IntegerOutOfRange { offset: 1, .. }
+        ));
+        assert!(error.to_string().ends_with("at `commit`"), "{error}");
+
+        encoded[1] = FieldElement::TWO;
+        let error = from_slice::<Tagged>(&encoded[..4]).unwrap_err();
+        let DecodeError::Context { offset, path, .. } = &error else {
+            panic!("{error}")
+        };
+        // the tag, the chain id and the state hash were read
+        assert_eq!(
+            (*offset, path.to_string()),
+            (4, "commit.public_data.size".into())
+        );
+    }
+
+    #[test]
+    fn test_native_config() {
+        use crate::config::{BoolMode, MapOrder};
+        use crate::{from_slice_with_config, to_field_elements_with_config};
+        use std::collections::BTreeMap;
+
+        let non_zero = Config {
+            bool_mode: BoolMode::NonZero,
+            ..Config::default()
+        };
+        let two = [FieldElement::TWO, FieldElement::ONE];
+        let (Native(flag), tail) =
+            from_slice_with_config::<(Native<bool>, u8)>(&two, non_zero).unwrap();
+        assert!(flag);
+        assert_eq!(tail, 1);
+        assert!(from_slice::<(Native<bool>, u8)>(&two).is_err());
+
+        let strict = Config {
+            map_order: MapOrder::Strict,
+            ..Config::default()
+        };
+        let map = Native(BTreeMap::from([(None, 1u8), (Some(2u8), 3)]));
+        assert!(to_field_elements(&map).is_ok());
+        let error = to_field_elements_with_config(&map, strict).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&EncodeError::MixedKeyLengths.to_string()));
+    }
 }
@@ -0,0 +1,42 @@
+//! Serde adapter that serializes a set with its elements sorted by their encoding, for use with
+//! `#[serde(with = "stark_util::sorted_set")]`.
+//!
+//! Serde serializes sets as sequences, which can't be told apart from a `Vec` by the serializer,
+//! so unlike maps a `HashSet` field is encoded in iteration order without this adapter. The
+//! elements are sorted and checked like the keys of a map, with the [crate::config::Config] of the
+//! encoder. Other serializers get the elements in iteration order.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The newtype name of the sets of this adapter, the encoder sorts the sequence it wraps.
+pub(crate) const SORTED_SET_NAME: &str = "$stark_util::SortedSet";
+
+/// The set as a sequence, its `Serialize` of a reference is `collect_seq` like the sets of std.
+struct Seq<'a, C>(&'a C);
+
+impl<'a, C, T> Serialize for Seq<'a, C>
+where
+    &'a C: IntoIterator<Item = &'a T>,
+    T: Serialize + 'a,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0)
+    }
+}
+
+pub fn serialize<'a, S, C, T>(set: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    &'a C: IntoIterator<Item = &'a T>,
+    T: Serialize + 'a,
+{
+    serializer.serialize_newtype_struct(SORTED_SET_NAME, &Seq(set))
+}
+
+pub fn deserialize<'de, D, C>(deserializer: D) -> Result<C, D::Error>
+where
+    D: Deserializer<'de>,
+    C: Deserialize<'de>,
+{
+    C::deserialize(deserializer)
+}
@@ -0,0 +1,284 @@
+use crate::array::Felt;
+use crate::decoder::DecoderImpl;
+use crate::der::de_owned::SerdeDecoder;
+use crate::der::reader::SliceReader;
+use crate::proto::StoredBlockInfo;
+use crate::serde::native_config;
+use crate::to_field_elements_with_config;
+use serde::de::{DeserializeOwned, Error as _, Visitor};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The newtype name of [Versioned], the serde encoder and decoder hand it their
+/// [crate::config::Config] like they do to [crate::serde::Native]
+pub(crate) const VERSIONED_NAME: &str = "$stark_util::Versioned";
+
+/// A struct whose encoding can gain fields, which are appended with `#[serde(default)]` so the
+/// payloads of older versions still decode in a [Versioned] envelope.
+pub trait Version {
+    /// The version of the current fields
+    const VERSION: u64;
+}
+
+impl Version for StoredBlockInfo {
+    const VERSION: u64 = 1;
+}
+
+/// Encodes `value` in an envelope `[version, len, ..elements]`.
+///
+/// The payload of an older version decodes with the `#[serde(default)]` of the fields missing at
+/// its end, and the elements of the fields added by a newer version are ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Versioned<T> {
+    /// The version of the payload, [Version::VERSION] of `T` when encoding
+    pub version: u64,
+    pub value: T,
+}
+
+impl<T: Version> Versioned<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            version: T::VERSION,
+            value,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Versioned<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Envelope<'a, T>(&'a Versioned<T>);
+
+        impl<T: Serialize> Serialize for Envelope<'_, T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let config = native_config().unwrap_or_default();
+                let elements = to_field_elements_with_config(&self.0.value, config)
+                    .map_err(S::Error::custom)?;
+                let elements: Vec<_> = elements.into_iter().map(Felt).collect();
+                (self.0.version, elements).serialize(serializer)
+            }
+        }
+
+        serializer.serialize_newtype_struct(VERSIONED_NAME, &Envelope(self))
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VersionedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: DeserializeOwned> Visitor<'de> for VersionedVisitor<T> {
+            type Value = Versioned<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a versioned {}", std::any::type_name::<T>())
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let (version, elements) = <(u64, Vec<Felt>)>::deserialize(deserializer)?;
+                let elements: Vec<_> = elements.into_iter().map(|e| e.0).collect();
+                let config = native_config().unwrap_or_default();
+                let mut decoder = DecoderImpl::with_config(SliceReader::new(&elements), config);
+                let value = T::deserialize(SerdeDecoder::allowing_missing_fields(&mut decoder))
+                    .map_err(D::Error::custom)?;
+                Ok(Versioned { version, value })
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(VERSIONED_NAME, VersionedVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::{DecodeError, EncodeError};
+    use crate::{from_slice, to_field_elements, U256};
+    use starknet::core::types::FieldElement;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct InfoV1 {
+        block_number: u64,
+        state_hash: U256,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct InfoV2 {
+        block_number: u64,
+        state_hash: U256,
+        #[serde(default)]
+        fee_account: u32,
+        #[serde(default)]
+        timestamps: Vec<u64>,
+    }
+
+    #[test]
+    fn test_versioned() {
+        let v1 = Versioned {
+            version: 1,
+            value: InfoV1 {
+                block_number: 3,
+                state_hash: U256::from(5u8),
+            },
+        };
+        let encoded = to_field_elements(&v1).unwrap();
+        assert_eq!(encoded[..3], [1u8, 3, 3].map(FieldElement::from));
+        assert_eq!(from_slice::<Versioned<InfoV1>>(&encoded).unwrap(), v1);
+
+        // old payloads get the default of the new fields
+        let v2: Versioned<InfoV2> = from_slice(&encoded).unwrap();
+        assert_eq!(v2.version, 1);
+        assert_eq!(
+            v2.value,
+            InfoV2 {
+                block_number: 3,
+                state_hash: U256::from(5u8),
+                fee_account: 0,
+                timestamps: vec![],
+            }
+        );
+
+        // the new fields are ignored by old decoders
+        let v2 = Versioned {
+            version: 2,
+            value: InfoV2 {
+                fee_account: 7,
+                timestamps: vec![1, 2],
+                ..v2.value
+            },
+        };
+        let encoded = to_field_elements(&v2).unwrap();
+        let v1: Versioned<InfoV1> = from_slice(&encoded).unwrap();
+        assert_eq!(v1.version, 2);
+        assert_eq!(v1.value.block_number, 3);
+        assert_eq!(from_slice::<Versioned<InfoV2>>(&encoded).unwrap(), v2);
+
+        // fields without default are required
+        #[derive(Debug, Deserialize)]
+        struct Required {
+            _block_number: u64,
+            _state_hash: U256,
+            _fee_account: u32,
+        }
+        let encoded = to_field_elements(Versioned::new(StoredBlockInfo::default())).unwrap();
+        assert!(from_slice::<Versioned<Required>>(&encoded).is_ok());
+        let encoded = to_field_elements(&v1).unwrap();
+        assert!(from_slice::<Versioned<Required>>(&encoded).is_err());
+        // but truncated structs are rejected outside of the envelope
+        assert!(from_slice::<InfoV2>(&encoded[2..]).is_err());
+
+        // and the fields of the struct can't miss their own fields
+        #[derive(Debug, Deserialize)]
+        struct Outer {
+            _info: InfoV2,
+            #[serde(default)]
+            _fee_account: u32,
+        }
+        let encoded = to_field_elements(&v1).unwrap();
+        assert!(from_slice::<Versioned<Outer>>(&encoded).is_err());
+        let encoded = to_field_elements(Versioned {
+            version: 1,
+            value: (3u8, U256::from(5u8), 7u8, Vec::<u64>::new()),
+        })
+        .unwrap();
+        assert!(from_slice::<Versioned<Outer>>(&encoded).is_ok());
+    }
+
+    #[test]
+    fn test_stored_block_info_versioned() {
+        let info = Versioned::new(StoredBlockInfo {
+            block_number: 10,
+            timestamp: 20,
+            ..Default::default()
+        });
+        let encoded = to_field_elements(&info).unwrap();
+        assert_eq!(encoded[0], FieldElement::from(StoredBlockInfo::VERSION));
+        assert_eq!(
+            from_slice::<Versioned<StoredBlockInfo>>(&encoded).unwrap(),
+            info
+        );
+    }
+
+    #[test]
+    fn test_unsupported_attributes() {
+        #[derive(Serialize)]
+        struct Skip {
+            a: u8,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            b: Option<u8>,
+        }
+        let err = to_field_elements(Skip { a: 1, b: None }).unwrap_err();
+        assert!(matches!(err, EncodeError::SkippedField("b")));
+        assert!(to_field_elements(Skip { a: 1, b: Some(2) }).is_ok());
+
+        #[derive(Serialize)]
+        struct Inner {
+            b: u8,
+        }
+        #[derive(Serialize)]
+        struct Flatten {
+            a: u8,
+            #[serde(flatten)]
+            inner: Inner,
+        }
+        let err = to_field_elements(Flatten {
+            a: 1,
+            inner: Inner { b: 2 },
+        })
+        .unwrap_err();
+        assert!(matches!(err, EncodeError::MapMustHaveLength));
+
+        // `#[serde(skip_serializing)]` can't be detected, the fields after it are shifted
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct SkipSerializing {
+            #[serde(skip_serializing)]
+            a: u8,
+            b: u8,
+            #[serde(skip)]
+            c: u8,
+        }
+        let encoded = to_field_elements(SkipSerializing { a: 1, b: 2, c: 3 }).unwrap();
+        assert_eq!(encoded, [FieldElement::TWO]);
+        let err = from_slice::<SkipSerializing>(&encoded).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            DecodeError::UnexpectedEnd { .. }
+        ));
+    }
+
+    #[test]
+    fn test_versioned_config() {
+        use crate::config::{BoolMode, Config, MapOrder};
+        use crate::{from_slice_with_config, to_field_elements_with_config};
+        use std::collections::BTreeMap;
+
+        // the payload is decoded with the config of the outer decoder
+        let non_zero = Config {
+            bool_mode: BoolMode::NonZero,
+            ..Config::default()
+        };
+        let encoded = [1u8, 1, 2].map(FieldElement::from);
+        let flag: Versioned<bool> = from_slice_with_config(&encoded, non_zero).unwrap();
+        assert!(flag.value);
+        assert!(from_slice::<Versioned<bool>>(&encoded).is_err());
+
+        // and encoded with the config of the outer encoder
+        let strict = Config {
+            map_order: MapOrder::Strict,
+            ..Config::default()
+        };
+        let map = Versioned {
+            version: 1,
+            value: BTreeMap::from([(None, 1u8), (Some(2u8), 3)]),
+        };
+        assert!(to_field_elements(&map).is_ok());
+        let error = to_field_elements_with_config(&map, strict).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&EncodeError::MixedKeyLengths.to_string()));
+    }
+}
@@ -0,0 +1,45 @@
+#[test]
+fn test_commit_block_info() {
+    let calldata = array![
+        0x7,
+        0x0,
+        0x14,
+        0x2,
+        0x102030405060708090a0b0c0d0e0f10,
+        0x11121314000000000000000000000000,
+        0x6553f10a,
+        0x1,
+        0x3,
+        0x1,
+        0xeeeeee00000000000000000000000000,
+        0x0,
+        0xb,
+        0x3,
+    ];
+    let expected = CommitBlockInfo {
+        new_state_hash: u256 { low: 7, high: 0 },
+        public_data: Bytes {
+            size: 20,
+            data: array![
+                1339673755198158349044581307228491536,
+                22690724222142916403703075827345260544,
+            ],
+        },
+        timestamp: 1700000010,
+        onchain_operations: array![
+            OnchainOperationData {
+                eth_witness: Bytes { size: 3, data: array![317596856862626935824257237918882463744] },
+                public_data_offset: 0,
+            },
+        ],
+        block_number: 11,
+        fee_account: 3,
+    };
+    let mut span = calldata.span();
+    let value: CommitBlockInfo = Serde::deserialize(ref span).unwrap();
+    assert(value == expected, 'deserialize mismatch');
+    assert(span.is_empty(), 'trailing elements');
+    let mut output = array![];
+    expected.serialize(ref output);
+    assert(output == calldata, 'serialize mismatch');
+}
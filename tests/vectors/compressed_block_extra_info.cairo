@@ -0,0 +1,16 @@
+#[test]
+fn test_compressed_block_extra_info() {
+    let calldata = array![0x1, 0x0, 0x2, 0x0, 0x2, 0x3, 0x0, 0x4, 0x0];
+    let expected = CompressedBlockExtraInfo {
+        public_data_hash: u256 { low: 1, high: 0 },
+        offset_commitment_hash: u256 { low: 2, high: 0 },
+        onchain_operation_pubdata_hashs: array![u256 { low: 3, high: 0 }, u256 { low: 4, high: 0 }],
+    };
+    let mut span = calldata.span();
+    let value: CompressedBlockExtraInfo = Serde::deserialize(ref span).unwrap();
+    assert(value == expected, 'deserialize mismatch');
+    assert(span.is_empty(), 'trailing elements');
+    let mut output = array![];
+    expected.serialize(ref output);
+    assert(output == calldata, 'serialize mismatch');
+}
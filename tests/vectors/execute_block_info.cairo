@@ -0,0 +1,48 @@
+#[test]
+fn test_execute_block_info() {
+    let calldata = array![
+        0xa,
+        0x2,
+        0x3,
+        0x0,
+        0x6553f100,
+        0x4,
+        0x5,
+        0x6,
+        0x0,
+        0xffffffffffffffffffffffffffffffff,
+        0x7,
+        0x1,
+        0x11,
+        0x2,
+        0x9090909090909090909090909090909,
+        0x9000000000000000000000000000000,
+    ];
+    let expected = ExecuteBlockInfo {
+        stored_block_info: StoredBlockInfo {
+            block_number: 10,
+            priority_operations: 2,
+            pending_onchain_operations_hash: u256 { low: 3, high: 0 },
+            timestamp: 1700000000,
+            state_hash: u256 { low: 4, high: 5 },
+            commitment: u256 { low: 6, high: 0 },
+            sync_hash: u256 { low: 340282366920938463463374607431768211455, high: 7 },
+        },
+        pending_onchain_ops_pubdata: array![
+            Bytes {
+                size: 17,
+                data: array![
+                    12009965891327239886942633203474172169,
+                    11963051962064242856134263542523101184,
+                ],
+            },
+        ],
+    };
+    let mut span = calldata.span();
+    let value: ExecuteBlockInfo = Serde::deserialize(ref span).unwrap();
+    assert(value == expected, 'deserialize mismatch');
+    assert(span.is_empty(), 'trailing elements');
+    let mut output = array![];
+    expected.serialize(ref output);
+    assert(output == calldata, 'serialize mismatch');
+}